@@ -0,0 +1,5 @@
+fn main() {
+    // migrations are embedded at compile time
+    // trigger recompilation when a migration is added or changed
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,13 @@
+-- create table for todo items
+-- based on struct TodoItem
+-- databases created before migrations were introduced already contain this table
+CREATE TABLE IF NOT EXISTS todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    content TEXT,
+    done INTEGER NOT NULL DEFAULT 0,
+    priority INTEGER,
+    creation_date INTEGER NOT NULL,
+    due_date INTEGER,
+    finish_date INTEGER
+);
@@ -0,0 +1,13 @@
+-- projects to group todo items into separate lists
+-- lists are ordered by position, then by id
+CREATE TABLE projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    color TEXT,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+-- todos without a project are in the inbox
+ALTER TABLE todos ADD COLUMN project_id INTEGER REFERENCES projects(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_project_id ON todos(project_id);
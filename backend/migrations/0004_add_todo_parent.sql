@@ -0,0 +1,5 @@
+-- subtasks reference their parent todo
+-- subtasks are deleted together with their parent
+ALTER TABLE todos ADD COLUMN parent_id INTEGER REFERENCES todos(id) ON DELETE CASCADE;
+
+CREATE INDEX todos_parent_id ON todos(parent_id);
@@ -0,0 +1,3 @@
+-- RFC 5545 recurrence rule, eg FREQ=WEEKLY;BYDAY=MO
+-- NULL = todo does not repeat
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
@@ -0,0 +1,26 @@
+-- full-text index over title and content of the todos
+-- the index stores no copy of the text, it reads it from the todos table
+CREATE VIRTUAL TABLE todos_fts USING fts5(
+    title,
+    content,
+    content = 'todos',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- keep the index in sync with the todos table
+CREATE TRIGGER todos_fts_insert AFTER INSERT ON todos BEGIN
+    INSERT INTO todos_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER todos_fts_delete AFTER DELETE ON todos BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+END;
+
+CREATE TRIGGER todos_fts_update AFTER UPDATE OF title, content ON todos BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO todos_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+-- index the existing todos
+INSERT INTO todos_fts (todos_fts) VALUES ('rebuild');
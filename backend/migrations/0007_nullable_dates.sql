@@ -0,0 +1,4 @@
+-- missing due and finish dates were stored as timestamp 0, use NULL instead
+-- dates are stored as epoch seconds or as RFC 3339 text
+UPDATE todos SET due_date = NULL WHERE due_date = 0 OR unixepoch(due_date) = 0;
+UPDATE todos SET finish_date = NULL WHERE finish_date = 0 OR unixepoch(finish_date) = 0;
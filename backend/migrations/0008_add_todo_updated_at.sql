@@ -0,0 +1,5 @@
+-- datetime of the last change of a todo, set by the server
+-- existing todos count as unchanged since they were created
+ALTER TABLE todos ADD COLUMN updated_at INTEGER;
+
+UPDATE todos SET updated_at = creation_date;
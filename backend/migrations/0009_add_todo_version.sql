@@ -0,0 +1,3 @@
+-- version of a todo, incremented with every change
+-- clients send it back in If-Match, so concurrent changes are detected
+ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,5 @@
+-- datetime a todo was moved to the trash
+-- trashed todos are hidden and purged after the retention period
+ALTER TABLE todos ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX todos_deleted_at ON todos(deleted_at);
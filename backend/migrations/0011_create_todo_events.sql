@@ -0,0 +1,16 @@
+-- history of all changes made to todos
+-- the ID only increases, so it orders the events and clients can continue after the last event they saw
+-- events have no foreign key, the history is kept after a todo is purged
+CREATE TABLE todo_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    todo_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    actor TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    before TEXT,
+    after TEXT
+);
+
+CREATE INDEX todo_events_todo_id ON todo_events(todo_id, id);
+CREATE INDEX todo_events_timestamp ON todo_events(timestamp);
@@ -0,0 +1,8 @@
+-- client session and request of a change, all changes of the last request of a session can be undone
+-- undone marks changes that were undone already, so the next undo goes one request further back
+ALTER TABLE todo_events ADD COLUMN session TEXT;
+ALTER TABLE todo_events ADD COLUMN request TEXT;
+ALTER TABLE todo_events ADD COLUMN undone INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX todo_events_session ON todo_events(session, id);
+CREATE INDEX todo_events_request ON todo_events(request);
@@ -0,0 +1,311 @@
+use std::{fmt, net::SocketAddr, path::PathBuf};
+
+use axum::http::{HeaderValue, header};
+use clap::Parser;
+use log::LevelFilter;
+use serde::Deserialize;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// Settings of the server that can be changed without recompiling.
+///
+/// Each value is taken from the first source that sets it:
+///
+/// 1. command line argument, eg `--bind-address 127.0.0.1:4000`
+/// 2. environment variable, eg `TICK_BIND_ADDRESS=127.0.0.1:4000`
+/// 3. TOML config file given with `--config` or `TICK_CONFIG`, eg `bind_address = "127.0.0.1:4000"`
+/// 4. the default value
+///
+/// Use [`Config::load()`] to build the configuration of the running process.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// address and port the server listens on
+    ///
+    /// - default `0.0.0.0:3000`
+    pub bind_address: SocketAddr,
+
+    /// SQLite database to connect to, created if it does not exist
+    ///
+    /// - default `sqlite://data/todos.db`
+    pub database_url: String,
+
+    /// maximum number of connections in the database pool
+    ///
+    /// - default `10`
+    pub pool_size: u32,
+
+    /// log level of the application, dependencies log at most `info`
+    ///
+    /// - default `debug`
+    pub log_level: LevelFilter,
+
+    /// number of todo items returned by [`list_todos()`](crate::handlers::list_todos) if no count is requested
+    ///
+    /// - default `25`
+    pub default_page_size: i64,
+
+    /// maximum number of todo items returned by [`list_todos()`](crate::handlers::list_todos)
+    ///
+    /// - default `100`
+    pub max_page_size: i64,
+
+    /// origins that are allowed to call the API from a browser
+    ///
+    /// - `*` allows every origin
+    /// - default is empty, no CORS headers are sent
+    pub cors_origins: Vec<String>,
+
+    /// maximum number of characters in the title of a todo item
+    ///
+    /// - default `200`
+    pub max_title_length: usize,
+
+    /// maximum number of characters in the content of a todo item
+    ///
+    /// - default `10000`
+    pub max_content_length: usize,
+
+    /// highest priority a todo item can have, the lowest is `0`
+    ///
+    /// - default `32767`
+    pub max_priority: i16,
+
+    /// maximum number of operations in one request to [`bulk_todos()`](crate::handlers::bulk::bulk_todos)
+    ///
+    /// - default `100`
+    pub max_bulk_operations: usize,
+
+    /// number of days a todo stays in the trash before it is purged
+    ///
+    /// - `0` keeps trashed todos until they are purged by a request
+    /// - default `30`
+    pub trash_retention_days: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            database_url: "sqlite://data/todos.db".to_string(),
+            pool_size: 10,
+            log_level: LevelFilter::Debug,
+            default_page_size: 25,
+            max_page_size: 100,
+            cors_origins: Vec::new(),
+            max_title_length: 200,
+            max_content_length: 10_000,
+            max_priority: i16::MAX,
+            max_bulk_operations: 100,
+            trash_retention_days: 30,
+        }
+    }
+}
+
+/// Partial configuration as given by one source.
+///
+/// Command line arguments and environment variables are parsed by clap,
+/// the config file is parsed by serde. Values that are not set stay `None`.
+#[derive(Debug, Default, Parser, Deserialize)]
+#[command(version, about = "Backend server of the tick todo app", long_about = None)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigArgs {
+    /// Path to a TOML config file
+    #[arg(long, env = "TICK_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+
+    /// Address and port the server listens on [default: 0.0.0.0:3000]
+    #[arg(long, env = "TICK_BIND_ADDRESS")]
+    pub bind_address: Option<SocketAddr>,
+
+    /// SQLite database to connect to [default: sqlite://data/todos.db]
+    #[arg(long, env = "TICK_DATABASE_URL")]
+    pub database_url: Option<String>,
+
+    /// Maximum number of database connections [default: 10]
+    #[arg(long, env = "TICK_POOL_SIZE")]
+    pub pool_size: Option<u32>,
+
+    /// Log level: off, error, warn, info, debug or trace [default: debug]
+    #[arg(long, env = "TICK_LOG_LEVEL")]
+    pub log_level: Option<LevelFilter>,
+
+    /// Number of todos returned if no count is requested [default: 25]
+    #[arg(long, env = "TICK_DEFAULT_PAGE_SIZE")]
+    pub default_page_size: Option<i64>,
+
+    /// Maximum number of todos returned per request [default: 100]
+    #[arg(long, env = "TICK_MAX_PAGE_SIZE")]
+    pub max_page_size: Option<i64>,
+
+    /// Allowed CORS origin, can be repeated or comma separated, `*` allows all
+    #[arg(long = "cors-origin", env = "TICK_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+
+    /// Maximum number of characters in the title of a todo [default: 200]
+    #[arg(long, env = "TICK_MAX_TITLE_LENGTH")]
+    pub max_title_length: Option<usize>,
+
+    /// Maximum number of characters in the content of a todo [default: 10000]
+    #[arg(long, env = "TICK_MAX_CONTENT_LENGTH")]
+    pub max_content_length: Option<usize>,
+
+    /// Highest priority of a todo [default: 32767]
+    #[arg(long, env = "TICK_MAX_PRIORITY")]
+    pub max_priority: Option<i16>,
+
+    /// Maximum number of operations in a bulk request [default: 100]
+    #[arg(long, env = "TICK_MAX_BULK_OPERATIONS")]
+    pub max_bulk_operations: Option<usize>,
+
+    /// Days a todo stays in the trash before it is purged, 0 keeps it [default: 30]
+    #[arg(long, env = "TICK_TRASH_RETENTION_DAYS")]
+    pub trash_retention_days: Option<u32>,
+}
+
+impl ConfigArgs {
+    /// Fill every value that is not set with the value of `other`.
+    pub fn or(self, other: ConfigArgs) -> ConfigArgs {
+        ConfigArgs {
+            config: self.config.or(other.config),
+            bind_address: self.bind_address.or(other.bind_address),
+            database_url: self.database_url.or(other.database_url),
+            pool_size: self.pool_size.or(other.pool_size),
+            log_level: self.log_level.or(other.log_level),
+            default_page_size: self.default_page_size.or(other.default_page_size),
+            max_page_size: self.max_page_size.or(other.max_page_size),
+            cors_origins: self.cors_origins.or(other.cors_origins),
+            max_title_length: self.max_title_length.or(other.max_title_length),
+            max_content_length: self.max_content_length.or(other.max_content_length),
+            max_priority: self.max_priority.or(other.max_priority),
+            max_bulk_operations: self.max_bulk_operations.or(other.max_bulk_operations),
+            trash_retention_days: self.trash_retention_days.or(other.trash_retention_days),
+        }
+    }
+}
+
+/// Errors that can occur while loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// config file could not be read
+    Io(PathBuf, std::io::Error),
+
+    /// config file is not valid TOML or contains unknown keys
+    Toml(PathBuf, toml::de::Error),
+
+    /// a value is out of range
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Cannot read config file {}: {}", path.display(), e),
+            ConfigError::Toml(path, e) => write!(f, "Invalid config file {}: {}", path.display(), e),
+            ConfigError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Load the configuration from the command line, the environment and the config file.
+    ///
+    /// See [`Config`] for the order of precedence.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::from_args(ConfigArgs::parse())
+    }
+
+    /// Build the configuration from already parsed arguments.
+    ///
+    /// If `args` points to a config file, the file is read and fills the values not set in `args`.
+    pub fn from_args(args: ConfigArgs) -> Result<Config, ConfigError> {
+        let file = match &args.config {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.clone(), e))?;
+                toml::from_str(&raw).map_err(|e| ConfigError::Toml(path.clone(), e))?
+            }
+            None => ConfigArgs::default(),
+        };
+
+        let args = args.or(file);
+        let default = Config::default();
+
+        let config = Config {
+            bind_address: args.bind_address.unwrap_or(default.bind_address),
+            database_url: args.database_url.unwrap_or(default.database_url),
+            pool_size: args.pool_size.unwrap_or(default.pool_size),
+            log_level: args.log_level.unwrap_or(default.log_level),
+            default_page_size: args.default_page_size.unwrap_or(default.default_page_size),
+            max_page_size: args.max_page_size.unwrap_or(default.max_page_size),
+            cors_origins: args.cors_origins.unwrap_or(default.cors_origins),
+            max_title_length: args.max_title_length.unwrap_or(default.max_title_length),
+            max_content_length: args.max_content_length.unwrap_or(default.max_content_length),
+            max_priority: args.max_priority.unwrap_or(default.max_priority),
+            max_bulk_operations: args.max_bulk_operations.unwrap_or(default.max_bulk_operations),
+            trash_retention_days: args.trash_retention_days.unwrap_or(default.trash_retention_days),
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that all values are in a usable range.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.pool_size == 0 {
+            return Err(ConfigError::Invalid("pool_size must be at least 1".to_string()));
+        }
+
+        if self.max_page_size < 1 {
+            return Err(ConfigError::Invalid("max_page_size must be at least 1".to_string()));
+        }
+
+        if self.default_page_size < 1 || self.default_page_size > self.max_page_size {
+            return Err(ConfigError::Invalid(format!("default_page_size must be between 1 and max_page_size ({})", self.max_page_size)));
+        }
+
+        if self.max_title_length == 0 {
+            return Err(ConfigError::Invalid("max_title_length must be at least 1".to_string()));
+        }
+
+        if self.max_priority < 0 {
+            return Err(ConfigError::Invalid("max_priority must not be negative".to_string()));
+        }
+
+        if self.max_bulk_operations == 0 {
+            return Err(ConfigError::Invalid("max_bulk_operations must be at least 1".to_string()));
+        }
+
+        for origin in &self.cors_origins {
+            if origin != "*" && HeaderValue::from_str(origin).is_err() {
+                return Err(ConfigError::Invalid(format!("Invalid CORS origin: {}", origin)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// CORS layer for the configured origins.
+    ///
+    /// Returns `None` if no origins are configured.
+    pub fn cors_layer(&self) -> Option<CorsLayer> {
+        if self.cors_origins.is_empty() {
+            return None;
+        }
+
+        let origins = if self.cors_origins.iter().any(|origin| origin == "*") {
+            AllowOrigin::from(Any)
+        } else {
+            //origins are checked in validate()
+            AllowOrigin::list(self.cors_origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+        };
+
+        Some(
+            CorsLayer::new()
+                .allow_origin(origins)
+                .allow_methods(Any)
+                .allow_headers(Any)
+                .expose_headers([header::LOCATION, header::ETAG]),
+        )
+    }
+}
@@ -0,0 +1,155 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::Arguments;
+use sqlx::sqlite::SqliteArguments;
+
+use crate::error::ApiError;
+
+/// Position in a sorted list of todo items.
+///
+/// The cursor holds the sort keys of the last todo of a page and the sorting it was created for.
+/// It is sent to the client as opaque token, see [`Cursor::encode()`].
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Cursor {
+    /// sorting the keys belong to, eg `due_date ASC NULLS LAST`
+    pub sort: String,
+
+    /// values of the sort keys, the ID of the todo is the last key
+    pub keys: Vec<Value>,
+}
+
+impl Cursor {
+    /// Token that is returned to the client as `next_cursor`.
+    pub fn encode(&self) -> String {
+        //a cursor is always valid json
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// Read a token sent by the client and check that it belongs to the sorting `sort`.
+    ///
+    /// Responds with [`ApiError::Validation`] if the token is not a cursor or was created for another sorting.
+    pub fn decode(token: &str, sort: &str) -> Result<Cursor, ApiError> {
+        let cursor: Cursor = URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| ApiError::Validation("Invalid cursor".to_string()))?;
+
+        if cursor.sort != sort {
+            return Err(ApiError::Validation("Cursor was created for another sorting, request the first page again".to_string()));
+        }
+
+        Ok(cursor)
+    }
+}
+
+/// Type of a sort key, decides how a key of a [`Cursor`] is bound to the query.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyKind {
+    /// integers and booleans
+    Integer,
+    /// floating point numbers, eg the relevance of a search
+    Real,
+    /// text
+    Text,
+    /// dates, kept as epoch seconds in the cursor
+    Date,
+}
+
+/// Column or expression a page is sorted by.
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey<'a> {
+    /// SQL expression of the key
+    pub expr: &'a str,
+    /// type of the key
+    pub kind: KeyKind,
+    /// `true` if sorted descending
+    pub descending: bool,
+    /// `true` if NULL values come first
+    pub nulls_first: bool,
+}
+
+/// Append the condition that selects the todos after the cursor to a query.
+///
+/// For sort keys `a, b` and cursor keys `x, y` this is `a > x OR (a IS x AND b > y)`,
+/// with the comparison and the handling of NULL depending on the direction of each key.
+///
+/// Responds with [`ApiError::Validation`] if the keys of the cursor do not fit the sort keys.
+pub fn push_keyset(query: &mut String, arguments: &mut SqliteArguments, sort_keys: &[SortKey], cursor: &Cursor) -> Result<(), ApiError> {
+    if cursor.keys.len() != sort_keys.len() {
+        return Err(ApiError::Validation("Invalid cursor".to_string()));
+    }
+
+    let mut alternatives: Vec<String> = Vec::new();
+
+    for (i, (key, value)) in sort_keys.iter().zip(&cursor.keys).enumerate() {
+        //nothing but other NULLs comes after NULL
+        if value.is_null() && !key.nulls_first {
+            continue;
+        }
+
+        //all previous keys are equal
+        let mut parts: Vec<String> = Vec::new();
+        for (previous, previous_value) in sort_keys[..i].iter().zip(&cursor.keys) {
+            parts.push(format!("{} IS ?", previous.expr));
+            add_key(arguments, previous.kind, previous_value)?;
+        }
+
+        //this key comes after the value of the cursor
+        let after = if value.is_null() {
+            format!("{} IS NOT NULL", key.expr)
+        } else {
+            let comparison = format!("{} {} ?", key.expr, if key.descending { "<" } else { ">" });
+            add_key(arguments, key.kind, value)?;
+
+            if key.nulls_first {
+                comparison
+            } else {
+                format!("({} OR {} IS NULL)", comparison, key.expr)
+            }
+        };
+
+        parts.push(after);
+        alternatives.push(format!("({})", parts.join(" AND ")));
+    }
+
+    //the cursor is on the last possible position
+    if alternatives.is_empty() {
+        query.push_str(" AND 0");
+    } else {
+        query.push_str(&format!(" AND ({})", alternatives.join(" OR ")));
+    }
+
+    Ok(())
+}
+
+/// Bind a key of a cursor as the type it has in the database.
+fn add_key(arguments: &mut SqliteArguments, kind: KeyKind, value: &Value) -> Result<(), ApiError> {
+    let invalid = || ApiError::Validation("Invalid cursor".to_string());
+
+    if value.is_null() {
+        let _ = arguments.add(None::<i64>);
+        return Ok(());
+    }
+
+    match kind {
+        KeyKind::Integer => {
+            let value = value.as_i64().or_else(|| value.as_bool().map(i64::from)).ok_or_else(invalid)?;
+            let _ = arguments.add(value);
+        }
+        KeyKind::Real => {
+            let _ = arguments.add(value.as_f64().ok_or_else(invalid)?);
+        }
+        KeyKind::Text => {
+            let _ = arguments.add(value.as_str().ok_or_else(invalid)?.to_string());
+        }
+        KeyKind::Date => {
+            let date: DateTime<Utc> = value.as_i64().and_then(|secs| DateTime::from_timestamp(secs, 0)).ok_or_else(invalid)?;
+            let _ = arguments.add(date);
+        }
+    }
+
+    Ok(())
+}
@@ -1,13 +1,15 @@
 // Jakob Frenzel
 // 10/12/25
 
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{self, Visitor}};
 use chrono::{DateTime, Utc};
 
 /// Struct that contains all data a todo item consists of.
 /// 
 /// This struct matches the database fields.
-#[derive(Deserialize, Serialize, sqlx::FromRow)]
+#[derive(Clone, Deserialize, Serialize, sqlx::FromRow)]
 pub struct TodoItem {
     /// unique id for the todo item
     pub id: i64,
@@ -29,23 +31,170 @@ pub struct TodoItem {
 
     /// datetime when item was created
     /// 
-    /// - timestamp created on client side
+    /// - set by the server, ignored when saving the todo
     /// - serialized as epoch seconds
-    #[serde(with = "chrono::serde::ts_seconds")]
+    #[serde(default, with = "chrono::serde::ts_seconds")]
     pub creation_date: DateTime<Utc>,
 
     /// datetime when the task should be finished
     /// 
+    /// - `None`, serialized as `null` = no due date
     /// - serialized as epoch seconds
-    #[serde(with = "chrono::serde::ts_seconds")]
-    pub due_date: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub due_date: Option<DateTime<Utc>>,
 
     /// datetime when the task actually was finished
     /// 
-    /// - timestamp created on client side
+    /// - `None`, serialized as `null` = not finished
+    /// - set by the server when the todo is marked as done, ignored when saving the todo
     /// - serialized as epoch seconds
-    #[serde(with = "chrono::serde::ts_seconds")]
-    pub finish_date: DateTime<Utc>
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub finish_date: Option<DateTime<Utc>>,
+
+    /// datetime of the last change of the todo
+    /// 
+    /// - set by the server, ignored when saving the todo
+    /// - `None`, serialized as `null` = never changed by the server
+    /// - serialized as epoch seconds
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// version of the todo, incremented with every change
+    /// 
+    /// - set by the server, ignored when saving the todo
+    /// - sent as `ETag`, send it back as `If-Match` to only change the todo if nobody else did in between
+    #[serde(default)]
+    pub version: i64,
+
+    /// datetime the todo was moved to the trash
+    /// 
+    /// - set by the server, ignored when saving the todo
+    /// - `None`, serialized as `null` = todo is not in the trash
+    /// - serialized as epoch seconds
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// names of the tags assigned to the todo
+    /// 
+    /// - stored in the `todo_tags` table, not a column of `todos`
+    /// - unknown tags are created when the todo is saved
+    #[serde(default)]
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+
+    /// project the todo belongs to
+    /// 
+    /// - `None` = todo is in the inbox
+    #[serde(default)]
+    pub project_id: Option<i64>,
+
+    /// parent todo if this todo is a subtask
+    /// 
+    /// - `None` = top-level todo
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+
+    /// RFC 5545 recurrence rule, eg `FREQ=WEEKLY;BYDAY=MO`
+    /// 
+    /// - `None` = todo does not repeat
+    /// - when the todo is marked as done, the next occurrence is created with the rule
+    #[serde(default)]
+    pub recurrence: Option<String>,
+
+    /// best matching part of title or content, matches are wrapped in `<mark>...</mark>`
+    /// 
+    /// - only filled when searching with `snippets=true`, ignored when saving the todo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub snippet: Option<String>,
+
+    /// relevance of the todo for the search, lower is better
+    /// 
+    /// - only filled when searching, ignored when saving the todo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub relevance: Option<f64>,
+
+    /// number of direct subtasks
+    /// 
+    /// - computed by the database, ignored when saving the todo
+    #[serde(default)]
+    #[sqlx(default)]
+    pub children_total: i64,
+
+    /// number of direct subtasks that are done
+    /// 
+    /// - computed by the database, ignored when saving the todo
+    #[serde(default)]
+    #[sqlx(default)]
+    pub children_done: i64,
+
+    /// subtasks of the todo
+    /// 
+    /// - only filled when requesting a tree from [`list_todos()`](crate::handlers::list_todos)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub children: Vec<TodoItem>,
+}
+
+/// Struct that contains a tag to categorise todo items.
+/// 
+/// This struct matches the database fields.
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Tag {
+    /// unique id for the tag
+    /// 
+    /// - ignored when creating a tag
+    #[serde(default)]
+    pub id: i64,
+
+    /// unique name of the tag, compared case insensitive
+    pub name: String,
+}
+
+/// Struct that contains a project to group todo items.
+/// 
+/// This struct matches the database fields.
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Project {
+    /// unique id for the project
+    /// 
+    /// - ignored when creating or updating a project
+    #[serde(default)]
+    pub id: i64,
+
+    /// name of the project
+    pub name: String,
+
+    /// colour of the project as hex string, eg `#1e90ff`
+    #[serde(default)]
+    pub color: Option<String>,
+
+    /// ordering of the projects, lower positions first
+    /// 
+    /// - projects with the same position are ordered by ID
+    #[serde(default)]
+    pub position: i64,
+}
+
+/// Struct that contains the query parameters to delete a project
+/// 
+/// Use this struct with [`delete_project()`](crate::handlers::projects::delete_project).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DeleteProjectParams {
+    /// What happens with the todos of the project.
+    /// 
+    /// See [`DeleteMode`] for possible values, defaults to [`DeleteMode::Inbox`].
+    pub mode: Option<DeleteMode>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// move the todos to the trash together with the project
+    Cascade,
+    /// keep the todos and move them to the inbox
+    Inbox,
 }
 
 /// [`list_todos()`]: crate::handlers::list_todos
@@ -53,12 +202,21 @@ pub struct TodoItem {
 /// 
 /// Use this struct with [`list_todos()`] to customize the results via
 /// pagination, sorting, filtering, and search.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct QueryParams {
     /// Maximum number of todo items to return.
     pub count: Option<i64>,
     /// Number of items to skip before returning results.
     pub offset: Option<i64>,
+    /// Opaque token to continue after the last todo of a page, see `next_cursor` of [`list_todos()`].
+    /// 
+    /// The cursor must be used with the same sorting, `offset` is ignored.
+    pub cursor: Option<String>,
+
+    /// Fields to sort by, see [`Sort`].
+    /// 
+    /// Replaces `sort_by` and `order`, eg `sort=-priority,due_date,title`.
+    pub sort: Option<Sort>,
 
     /// Field to sort by.
     /// 
@@ -69,25 +227,572 @@ pub struct QueryParams {
     /// See [`Order`] for possible values.
     pub order: Option<Order>,
 
+    /// Position of todos without value in the sorted column, eg without due date.
+    /// 
+    /// See [`Nulls`] for possible values, default is `last`.
+    pub nulls: Option<Nulls>,
+
     /// Filter todos by completion status.
-    pub done: Option<bool>,
+    /// 
+    /// See [`DoneFilter`] for possible values, `any` is the same as no filter.
+    pub done: Option<DoneFilter>,
+
+    /// Filter todos that are not done and past their due date, `false` returns all other todos.
+    pub overdue: Option<bool>,
+
+    /// Filter todos due before this time, exclusive.
+    /// 
+    /// - epoch seconds
+    /// - todos without due date never match a date filter, same for the other date filters
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub due_before: Option<DateTime<Utc>>,
+    /// Filter todos due at or after this time, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub due_after: Option<DateTime<Utc>>,
+
+    /// Filter todos created before this time, exclusive, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Filter todos created at or after this time, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Filter todos finished before this time, exclusive, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub finished_before: Option<DateTime<Utc>>,
+    /// Filter todos finished at or after this time, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub finished_after: Option<DateTime<Utc>>,
+
+    /// Filter todos changed at or after this time, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub updated_since: Option<DateTime<Utc>>,
+
+    /// Filter todos with at least this priority.
+    pub priority_min: Option<i16>,
+    /// Filter todos with at most this priority.
+    pub priority_max: Option<i16>,
 
-    /// Search string to filter todos by title or content.
+    /// Full-text search in title and content.
+    /// 
+    /// Results are ordered by relevance unless `sort` or `sort_by` is set.
+    /// Words ending with `*` match as prefix, text in double quotes matches as phrase,
+    /// see [`match_query()`](crate::search::match_query).
     pub search: Option<String>,
+
+    /// Add a `snippet` with the highlighted matches of the search to each todo.
+    pub snippets: Option<bool>,
+
+    /// Filter todos that have this tag.
+    pub tag: Option<String>,
+    /// Comma separated list of tags, filter todos that have at least one of them.
+    pub any_tag: Option<String>,
+    /// Comma separated list of tags, filter todos that have all of them.
+    pub all_tags: Option<String>,
+
+    /// Filter todos by project.
+    /// 
+    /// - ID of the project
+    /// - `inbox` = todos without a project
+    pub project: Option<String>,
+
+    /// Return only todos that are not a subtask.
+    pub top_level: Option<bool>,
+    /// Return top-level todos with all of their subtasks nested in `children`.
+    /// 
+    /// Filters and pagination apply to the top-level todos, subtasks are always included.
+    pub tree: Option<bool>,
+
+    /// Return the todos in the trash instead of the other todos, see [`list_trash()`](crate::handlers::trash::list_trash).
+    pub trashed: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Struct that contains the query parameters to update a todo item
+/// 
+/// Use this struct with [`update_todo()`](crate::handlers::update_todo) and [`patch_todo()`](crate::handlers::patch_todo).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateParams {
+    /// Mark all subtasks as done too when the todo is marked as done.
+    pub complete_children: Option<bool>,
+}
+
+/// Struct that contains the query parameters to preview the occurrences of a recurring todo
+/// 
+/// Use this struct with [`list_occurrences()`](crate::handlers::list_occurrences).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OccurrenceParams {
+    /// Number of occurrences to return, default is 5.
+    pub count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SortBy {
     CreationDate,
     DueDate,
     Priority,
     Done,
+    Title,
+    FinishDate,
+    UpdatedAt,
+    DeletedAt,
+    Id,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Order {
     Asc,
     Desc,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+/// Field and direction of one sort key.
+#[derive(Debug, Clone, Copy)]
+pub struct SortField {
+    pub by: SortBy,
+    pub order: Order,
+}
+
+/// Sort keys in order of precedence.
+/// 
+/// Serialized as comma separated list of [`SortBy`] fields, a leading `-` sorts the field descending,
+/// eg `-priority,due_date,title`.
+#[derive(Debug, Clone)]
+pub struct Sort(pub Vec<SortField>);
+
+impl Serialize for Sort {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = self.0
+            .iter()
+            .map(|field| {
+                //all fields serialize to a string
+                let name = serde_json::to_value(field.by).ok().and_then(|name| name.as_str().map(str::to_string)).unwrap_or_default();
+                match field.order {
+                    Order::Asc => name,
+                    Order::Desc => format!("-{}", name),
+                }
+            })
+            .collect::<Vec<String>>();
+
+        serializer.serialize_str(&fields.join(","))
+    }
+}
+
+impl<'de> Deserialize<'de> for Sort {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SortVisitor;
+
+        impl Visitor<'_> for SortVisitor {
+            type Value = Sort;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("comma separated fields to sort by, eg -priority,due_date")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Sort, E> {
+                let mut fields = Vec::new();
+
+                for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                    let (name, order) = match name.strip_prefix('-') {
+                        Some(name) => (name, Order::Desc),
+                        None => (name.strip_prefix('+').unwrap_or(name), Order::Asc),
+                    };
+
+                    let by = SortBy::deserialize(de::value::StrDeserializer::<E>::new(name))?;
+                    fields.push(SortField { by, order });
+                }
+
+                if fields.is_empty() {
+                    return Err(E::invalid_value(de::Unexpected::Str(value), &self));
+                }
+
+                Ok(Sort(fields))
+            }
+        }
+
+        deserializer.deserialize_str(SortVisitor)
+    }
+}
+
+/// Completion status to filter todos by.
+/// 
+/// Serialized as `true`, `false` or `any`. Booleans and the strings `"true"` and `"false"`
+/// are both accepted, so the filter works in query strings and in json.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoneFilter {
+    /// `true`, only todos that are done
+    Done,
+    /// `false`, only todos that are not done
+    Open,
+    /// `any`, todos with any status
+    Any,
+}
+
+impl Serialize for DoneFilter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DoneFilter::Done => serializer.serialize_bool(true),
+            DoneFilter::Open => serializer.serialize_bool(false),
+            DoneFilter::Any => serializer.serialize_str("any"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DoneFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DoneFilterVisitor;
+
+        impl Visitor<'_> for DoneFilterVisitor {
+            type Value = DoneFilter;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("true, false or any")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<DoneFilter, E> {
+                Ok(if value { DoneFilter::Done } else { DoneFilter::Open })
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<DoneFilter, E> {
+                match value {
+                    "true" => Ok(DoneFilter::Done),
+                    "false" => Ok(DoneFilter::Open),
+                    "any" => Ok(DoneFilter::Any),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(DoneFilterVisitor)
+    }
+}
+
+/// Struct that contains the fields of a partial update.
+/// 
+/// Every field is optional, only fields present in the request body are written
+/// to the database. ID, creation date and finish date cannot be changed and are ignored.
+/// 
+/// Use this struct with [`patch_todo()`](crate::handlers::patch_todo).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TodoPatch {
+    /// new title of the todo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// new content of the todo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    /// new done status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done: Option<bool>,
+
+    /// new priority
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i16>,
+
+    /// new due date
+    /// 
+    /// - `Some(None)`, serialized as `null` = remove the due date
+    /// - serialized as epoch seconds
+    #[serde(default, with = "ts_seconds_patch", skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<Option<DateTime<Utc>>>,
+
+    /// new list of tags, replaces all tags of the todo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+
+    /// new project of the todo
+    /// 
+    /// - `Some(None)`, serialized as `null` = move todo to the inbox
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<Option<i64>>,
+
+    /// new parent of the todo
+    /// 
+    /// - `Some(None)`, serialized as `null` = make todo a top-level todo
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Option<i64>>,
+
+    /// new recurrence rule of the todo
+    /// 
+    /// - `Some(None)`, serialized as `null` = todo does not repeat anymore
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Option<String>>,
+}
+
+/// Kind of change recorded in the history of a todo item
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Operation {
+    /// todo was created, also the next occurrence of a recurring todo
+    Create,
+    /// fields of the todo were changed
+    Update,
+    /// todo was completed or reopened
+    Toggle,
+    /// todo was moved to the trash
+    Delete,
+    /// todo was restored from the trash
+    Restore,
+    /// todo was deleted for good
+    Purge,
+    /// fields of the todo were set back to an earlier revision
+    Revert,
+    /// change of a client session was undone
+    Undo,
+}
+
+/// Struct that contains a recorded change of a todo item
+/// 
+/// Returned by [`todo_history()`](crate::handlers::history::todo_history) and [`list_events()`](crate::handlers::history::list_events).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TodoEvent {
+    /// unique id of the event, increases with every change
+    pub id: i64,
+
+    /// todo that was changed
+    pub todo_id: i64,
+
+    /// version of the todo after the change, the version before a purge
+    pub version: i64,
+
+    /// datetime of the change
+    /// 
+    /// - serialized as epoch seconds
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+
+    /// who made the change, from the `X-Actor` header of the request
+    pub actor: String,
+
+    /// kind of change
+    pub operation: Operation,
+
+    /// changed fields with their value before the change
+    /// 
+    /// - `None` = todo was created
+    /// - all fields if the todo was purged
+    pub before: Option<serde_json::Value>,
+
+    /// changed fields with their value after the change
+    /// 
+    /// - `None` = todo was purged
+    /// - all fields if the todo was created
+    pub after: Option<serde_json::Value>,
+}
+
+/// Struct that contains the query parameters to filter the history of todo items
+/// 
+/// Use this struct with [`list_events()`](crate::handlers::history::list_events).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EventParams {
+    /// Maximum number of events to return.
+    pub count: Option<i64>,
+
+    /// Return only events after the event with this ID, eg the last event of the previous page.
+    pub after_id: Option<i64>,
+
+    /// Filter events of this todo.
+    pub todo_id: Option<i64>,
+
+    /// Filter events made by this actor.
+    pub actor: Option<String>,
+
+    /// Filter events of this kind, see [`Operation`].
+    pub operation: Option<Operation>,
+
+    /// Filter events at or after this time, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Filter events before this time, exclusive, epoch seconds.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Kind of a live notification about a todo item
+/// 
+/// Derived from the recorded change, eg restoring a todo from the trash creates it again for clients.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// todo was created or came back from the trash
+    Created,
+    /// fields of the todo were changed
+    Updated,
+    /// todo was moved to the trash or purged
+    Deleted,
+    /// todo was completed or reopened
+    Toggled,
+}
+
+impl ChangeKind {
+    /// Name of the server-sent event.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Toggled => "toggled",
+        }
+    }
+}
+
+/// Struct that contains a live notification about a changed todo item
+/// 
+/// Sent by [`stream_events()`](crate::handlers::stream::stream_events) for every recorded [`TodoEvent`].
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TodoNotification {
+    /// ID of the recorded event, send it as `Last-Event-ID` to resume the stream after it
+    pub event_id: i64,
+
+    /// kind of change
+    pub kind: ChangeKind,
+
+    /// recorded operation, see [`Operation`]
+    pub operation: Operation,
+
+    /// todo that was changed
+    pub todo_id: i64,
+
+    /// who made the change, from the `X-Actor` header of the request
+    pub actor: String,
+
+    /// datetime of the change
+    /// 
+    /// - serialized as epoch seconds
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+
+    /// current state of the todo, including todos in the trash
+    /// 
+    /// - `None` = todo was purged
+    pub item: Option<TodoItem>,
+}
+
+/// Struct that contains the query parameters of the event stream
+/// 
+/// Use this struct with [`stream_events()`](crate::handlers::stream::stream_events).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StreamParams {
+    /// Start after the event with this ID, like the `Last-Event-ID` header. Without both only new events are sent.
+    pub after_id: Option<i64>,
+}
+
+/// Struct that contains the query parameters of a sync
+/// 
+/// Use this struct with [`sync_todos()`](crate::handlers::sync::sync_todos).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SyncParams {
+    /// Token of the previous sync, only changes after it are returned. Without token all todos are returned.
+    pub since: Option<i64>,
+}
+
+/// Struct that marks a todo item that was deleted since the last sync
+/// 
+/// Returned by [`sync_todos()`](crate::handlers::sync::sync_todos) for todos in the trash and purged todos.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Tombstone {
+    /// ID of the deleted todo
+    pub id: i64,
+
+    /// datetime of the deletion
+    /// 
+    /// - serialized as epoch seconds
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Struct that contains the query parameters of a revert
+/// 
+/// Use this struct with [`revert_todo()`](crate::handlers::history::revert_todo).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RevertParams {
+    /// Version of the todo to go back to, see the `version` of a [`TodoEvent`].
+    pub revision: i64,
+}
+
+/// Struct that contains the body of a bulk request
+/// 
+/// Use this struct with [`bulk_todos()`](crate::handlers::bulk::bulk_todos).
+#[derive(Deserialize, Serialize)]
+pub struct BulkRequest {
+    /// operations applied in order, either all or none of them are saved
+    pub operations: Vec<BulkOperation>,
+}
+
+/// Single operation of a [`BulkRequest`], selected by the `op` field.
+/// 
+/// `version` works like the `If-Match` header of the single endpoints,
+/// the operation fails if the todo has another version.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BulkOperation {
+    /// create a todo like [`add_todo()`](crate::handlers::add_todo)
+    Create {
+        item: TodoItem,
+    },
+
+    /// change the fields of a todo like [`patch_todo()`](crate::handlers::patch_todo)
+    Update {
+        id: i64,
+        patch: TodoPatch,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<i64>,
+    },
+
+    /// delete a todo like [`delete_todo()`](crate::handlers::delete_todo)
+    Delete {
+        id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<i64>,
+    },
+
+    /// toggle the done status like [`toggle_todo()`](crate::handlers::toggle_todo)
+    Toggle {
+        id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<i64>,
+    },
+}
+
+/// Deserialize a value that is present in the json, including `null`, as `Some`.
+/// 
+/// Together with `#[serde(default)]` this distinguishes a missing field (`None`)
+/// from a field set to `null` (`Some(None)`).
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Serialize an optional timestamp of a [`TodoPatch`] as epoch seconds, including `null`.
+/// 
+/// Like [`deserialize_some()`], a timestamp that is present in the json is `Some`,
+/// so `null` removes the timestamp.
+mod ts_seconds_patch {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<Option<DateTime<Utc>>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(Some(date)) => serializer.serialize_some(&date.timestamp()),
+            _ => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<DateTime<Utc>>>, D::Error> {
+        chrono::serde::ts_seconds_option::deserialize(deserializer).map(Some)
+    }
+}
@@ -0,0 +1,25 @@
+use log::info;
+use sqlx::{migrate::{MigrateError, Migrator}, sqlite::SqlitePool};
+
+/// Schema migrations of the database.
+/// 
+/// The migrations are embedded from the `migrations` directory at compile time.
+/// Each migration is a file `<version>_<description>.sql` and is applied exactly once, in order of its version.
+/// 
+/// Applied versions are recorded in the `_sqlx_migrations` table of the database.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Apply all pending migrations to the database.
+/// 
+/// Migrations that are already recorded in the database are skipped,
+/// so this can be called on every startup.
+pub async fn run_migrations(connection: &SqlitePool) -> Result<(), MigrateError> {
+    MIGRATOR.run(connection).await?;
+
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(connection)
+        .await?;
+
+    info!("Database schema version: {}", version.unwrap_or(0));
+    Ok(())
+}
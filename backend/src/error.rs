@@ -0,0 +1,131 @@
+use std::fmt;
+
+use axum::{Json, http::StatusCode, response::{IntoResponse, Response}};
+use log::error;
+use serde_json::json;
+
+use crate::validation::FieldError;
+
+/// Error type shared by all handlers.
+///
+/// Every variant maps to a HTTP status code. The response body keeps the
+/// `{"status": "error", "message": "..."}` layout, so clients can still show the message.
+#[derive(Debug)]
+pub enum ApiError {
+    /// Requested resource does not exist.
+    ///
+    /// Responds with `404 Not Found`.
+    NotFound(String),
+
+    /// Request was understood but contains invalid values.
+    ///
+    /// Responds with `422 Unprocessable Entity`.
+    Validation(String),
+
+    /// Request body contains fields with invalid values, eg an empty title.
+    ///
+    /// Responds with `422 Unprocessable Entity`, the body lists every field in `errors`.
+    InvalidFields(Vec<FieldError>),
+
+    /// Request conflicts with the current state of a resource, eg a duplicate name.
+    ///
+    /// Responds with `409 Conflict`.
+    Conflict(String),
+
+    /// Resource was changed since the client read it, the `If-Match` header does not match.
+    ///
+    /// Responds with `412 Precondition Failed`.
+    PreconditionFailed(String),
+
+    /// Request body could not be parsed.
+    ///
+    /// Responds with `400 Bad Request`.
+    InvalidJson(String),
+
+    /// Operation of a bulk request failed, none of the operations were saved.
+    ///
+    /// Responds with the status code of the failed operation, the body contains its position in `index`.
+    Bulk(usize, Box<ApiError>),
+
+    /// Query to the database failed.
+    ///
+    /// Responds with `500 Internal Server Error`.
+    Database(sqlx::Error),
+}
+
+impl ApiError {
+    /// HTTP status code the error is answered with.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ApiError::InvalidJson(_) => StatusCode::BAD_REQUEST,
+            ApiError::Bulk(_, e) => e.status_code(),
+            ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotFound(msg) => write!(f, "{}", msg),
+            ApiError::Validation(msg) => write!(f, "{}", msg),
+            ApiError::InvalidFields(errors) => {
+                let fields = errors
+                    .iter()
+                    .map(|error| format!("{} {}", error.field, error.message))
+                    .collect::<Vec<String>>();
+                write!(f, "Invalid fields: {}", fields.join(", "))
+            }
+            ApiError::Conflict(msg) => write!(f, "{}", msg),
+            ApiError::PreconditionFailed(msg) => write!(f, "{}", msg),
+            ApiError::InvalidJson(msg) => write!(f, "Invalid JSON: {}", msg),
+            ApiError::Bulk(index, e) => write!(f, "Operation {} failed: {}", index, e),
+            ApiError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::Database(e)
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::InvalidJson(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error!("Handler result: {:?}", self);
+
+        let mut body = json!({
+            "status": "error",
+            "message": self.to_string()
+        });
+
+        //the failed operation of a bulk request adds its position
+        let error = match &self {
+            ApiError::Bulk(index, e) => {
+                body["index"] = json!(index);
+                e.as_ref()
+            }
+            e => e,
+        };
+
+        if let ApiError::InvalidFields(errors) = error {
+            body["errors"] = json!(errors);
+        }
+
+        (self.status_code(), Json(body)).into_response()
+    }
+}
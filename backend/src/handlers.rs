@@ -1,20 +1,83 @@
 // Jakob Frenzel
 // 10/12/25
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use axum::{Json, body::Bytes, extract::{Path, Query, State}, response::IntoResponse};
-use log::{debug, error};
-use serde_json::json;
-use sqlx::sqlite::{SqlitePool, SqliteQueryResult};
-use sqlx::Arguments;
+use axum::{Json, body::Bytes, extract::{Path, Query, State}, http::{HeaderMap, HeaderName, StatusCode, header}, response::IntoResponse};
+use chrono::{DateTime, SubsecRound, Utc};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteArguments, SqlitePool, SqliteQueryResult};
+use sqlx::{Arguments, SqliteConnection};
 
-use crate::data_structs::{Order, QueryParams, SortBy, TodoItem};
+use crate::config::Config;
+use crate::data_structs::{DoneFilter, Nulls, OccurrenceParams, Operation, Order, QueryParams, SortBy, SortField, TodoItem, TodoPatch, UpdateParams};
+use crate::error::ApiError;
+use crate::handlers::projects::{check_project, unknown_project};
+use crate::handlers::tags::{load_tags, set_tags, split_tags};
+use crate::recurrence::{next_occurrence, next_occurrences};
+use crate::search::{RANK, SNIPPET, autocomplete_query, match_query};
+use crate::validation::{FieldError, check_recurrence, into_result, push, validate_patch, validate_todo};
+use crate::history::{Actor, actor, record, record_all, snapshot, snapshots};
+use crate::cursor::{Cursor, KeyKind, SortKey, push_keyset};
+
+/// Endpoints for tags
+pub mod tags;
+
+/// Endpoints for projects
+pub mod projects;
+
+/// Endpoints that change many todos at once
+pub mod bulk;
+
+/// Endpoints for the trash of deleted todos
+pub mod trash;
+
+/// Endpoints for the change history of todos
+pub mod history;
+
+/// Endpoint for live notifications about changed todos
+pub mod stream;
+
+/// Endpoint for clients that keep a copy of the todos
+pub mod sync;
+
+/// Columns of the `todos` table that make up a [`TodoItem`].
+/// 
+/// Select them `FROM todos` without an alias, the subtask counts refer to `todos.id`.
+/// The columns are qualified, so `todos_fts` can be joined for a full-text search.
+const TODO_COLUMNS: &str = "
+    todos.id, todos.title, todos.content, todos.done, todos.priority,
+    todos.creation_date, todos.due_date, todos.finish_date, todos.updated_at, todos.version, todos.deleted_at,
+    todos.project_id, todos.parent_id, todos.recurrence,
+    (SELECT COUNT(*) FROM todos c WHERE c.parent_id = todos.id AND c.deleted_at IS NULL) AS children_total,
+    (SELECT COUNT(*) FROM todos c WHERE c.parent_id = todos.id AND c.deleted_at IS NULL AND c.done) AS children_done
+";
+
+/// Assignment of the finish date for a new done status, bind the status and the current time.
+/// 
+/// A todo that is completed gets the current time, a todo that stays done keeps its finish date
+/// and a reopened todo loses it, the same as [`change_done()`] does.
+const SET_FINISH_DATE: &str = "finish_date = CASE WHEN NOT ? THEN NULL WHEN done THEN finish_date ELSE ? END";
 
 /// Returns a paginated list of todo items.
 /// 
 /// By default, this handler returns `25` todo items starting at offset `0`.
 /// The result set can be customized using optional query parameters defined in [`QueryParams`].
+/// The default and maximum number of items are set by [`Config`].
+/// 
+/// Besides the `items` the response contains the pagination:
+/// 
+/// - `total` = number of todo items matching the filters and search on all pages
+/// - `count` and `offset` = size and start of this page, `offset` is `null` for a page requested with a cursor
+/// - `next` and `prev` = links to the neighbouring pages with the same filters, `null` on the last and first page
+/// - `next_cursor` = token to request the next page with `cursor`, `null` on the last page
+/// 
+/// Pages requested with a cursor continue exactly after the last todo of the previous page,
+/// even if todo items were added or deleted in between. Only the next page can be linked.
+/// 
+/// The `search` uses a full-text index and matches whole words or word prefixes like `deploy*`,
+/// not arbitrary parts of words: `est` does not find `test`, `est*` does not either.
 /// 
 /// # Examples
 /// 
@@ -30,88 +93,436 @@ use crate::data_structs::{Order, QueryParams, SortBy, TodoItem};
 /// curl -X GET http://localhost:3000/todos?count=2&offset=10
 /// ```
 /// 
+/// Get todo items by descending priority, then by due date and title:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?sort=-priority,due_date,title
+/// ```
+/// 
+/// Get the page after a `next_cursor` of a previous response, with the same sorting:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?sort_by=due_date&order=asc&cursor=eyJzb3J0Ijoi...
+/// ```
+/// 
 /// Get todo items that are done:
 /// 
 /// ```bash
 /// curl -X GET http://localhost:3000/todos?done=true
 /// ```
+/// 
+/// Get overdue todo items with a priority of at least `2`:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?overdue=true&priority_min=2
+/// ```
+/// 
+/// Get todo items due in the first week of 2026, dates are epoch seconds:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?due_after=1767225600&due_before=1767830400
+/// ```
+/// 
+/// Get todo items of project `3`, use `project=inbox` for todo items without a project:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?project=3
+/// ```
+/// 
+/// Get top-level todo items with their subtasks nested in `children`:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?tree=true
+/// ```
+/// 
+/// Get todo items changed since a previous request, dates are epoch seconds:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?updated_since=1767225600
+/// ```
+/// 
+/// Get todo items tagged with `work` or `home`:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos?any_tag=work,home
+/// ```
+/// 
+/// Search for todo items containing the phrase `weekly review` and a word starting with `deploy`, with highlighted snippets:
+/// 
+/// ```bash
+/// curl -G http://localhost:3000/todos --data-urlencode 'search="weekly review" deploy*' -d snippets=true
+/// ```
 // https://docs.rs/axum/latest/axum/extract/struct.Query.html
-pub async fn list_todos(State(connection): State<SqlitePool>, Query(params): Query<QueryParams>) -> impl IntoResponse {
+pub async fn list_todos(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Query(params): Query<QueryParams>) -> Result<Json<Value>, ApiError> {
     debug!("Called handler list_todos() with {:?}", params);
 
     // pagination
-    let count = params.count.unwrap_or(25).clamp(1, 100);
+    let count = params.count.unwrap_or(config.default_page_size).clamp(1, config.max_page_size);
     let offset = params.offset.unwrap_or(0).max(0);
 
-    let sort_column = match params.sort_by.unwrap_or(SortBy::CreationDate) {
-        SortBy::CreationDate => "creation_date",
-        SortBy::DueDate => "due_date",
-        SortBy::Priority => "priority",
-        SortBy::Done => "done",
+    //full-text search joins the index
+    //a search without words does not filter
+    let search = params.search.as_deref().and_then(match_query);
+    let snippets = search.is_some() && params.snippets.unwrap_or(false);
+
+    //search results are ordered by relevance unless a sorting is requested
+    let by_relevance = search.is_some() && params.sort.is_none() && params.sort_by.is_none();
+
+    //a list of sort keys replaces sort_by and order
+    let mut fields = match &params.sort {
+        Some(sort) => sort.0.clone(),
+        None => vec![SortField {
+            by: params.sort_by.unwrap_or(SortBy::CreationDate),
+            order: params.order.unwrap_or(Order::Desc),
+        }],
     };
 
-    let sort_order = match params.order.unwrap_or(Order::Desc) {
-        Order::Asc => "ASC",
-        Order::Desc => "DESC",
+    //the ID is the last key, so the order is unique and a cursor points to exactly one position
+    //it follows the direction of the first key
+    if by_relevance {
+        fields.clear();
+    } else if !fields.iter().any(|field| matches!(field.by, SortBy::Id)) {
+        fields.push(SortField { by: SortBy::Id, order: fields[0].order });
+    }
+
+    //todos without due date are last by default
+    let nulls_first = matches!(params.nulls.unwrap_or(Nulls::Last), Nulls::First);
+
+    let sort_keys = if by_relevance {
+        vec![
+            SortKey { expr: RANK, kind: KeyKind::Real, descending: false, nulls_first: false },
+            SortKey { expr: "todos.id", kind: KeyKind::Integer, descending: false, nulls_first: false },
+        ]
+    } else {
+        fields
+            .iter()
+            .map(|field| {
+                let (expr, kind) = sort_column(field.by);
+                SortKey { expr, kind, descending: matches!(field.order, Order::Desc), nulls_first }
+            })
+            .collect()
+    };
+
+    let order_by = sort_keys
+        .iter()
+        .map(|key| format!(
+            "{} {} {}",
+            key.expr,
+            if key.descending { "DESC" } else { "ASC" },
+            if key.nulls_first { "NULLS FIRST" } else { "NULLS LAST" },
+        ))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    //continue after the last todo of the previous page
+    let cursor = match &params.cursor {
+        Some(token) => Some(Cursor::decode(token, &order_by)?),
+        None => None,
     };
 
     // base query
-    let mut arguments = sqlx::sqlite::SqliteArguments::default();
-    let mut query = format!("
-        SELECT id, title, content, done, priority,
-               creation_date, due_date, finish_date
-        FROM todos
+    // the filters are shared by the page and the total
+    let TodoFilter { mut sql, mut arguments, .. } = todo_filter(&params, search.as_deref())?;
+    let tree = params.tree.unwrap_or(false);
+
+    //number of todos on all pages
+    let total: i64 = sqlx::query_scalar_with(&format!("SELECT COUNT(*) {}", sql), arguments.clone())
+        .fetch_one(&connection)
+        .await?;
+
+    //a cursor replaces the offset
+    let offset = match &cursor {
+        Some(cursor) => {
+            push_keyset(&mut sql, &mut arguments, &sort_keys, cursor)?;
+            0
+        }
+        None => offset,
+    };
+
+    //sorting and finally pagination
+    //one more todo than requested tells if there is a next page
+    let query = format!(
+        "SELECT {}{}{} {} ORDER BY {} LIMIT ? OFFSET ?",
+        TODO_COLUMNS,
+        if search.is_some() { format!(", {} AS relevance", RANK) } else { String::new() },
+        if snippets { format!(", {} AS snippet", SNIPPET) } else { String::new() },
+        sql,
+        order_by,
+    );
+    let _ = arguments.add(count + 1);
+    let _ = arguments.add(offset);
+
+    let mut items: Vec<TodoItem> = sqlx::query_as_with::<_, TodoItem, _>(&query, arguments)
+        .fetch_all(&connection)
+        .await?;
+
+    let has_next = items.len() as i64 > count;
+    items.truncate(count as usize);
+
+    //cursor of the last todo on this page
+    let next_cursor = items.last().filter(|_| has_next).map(|item| {
+        let keys = if by_relevance {
+            vec![json!(item.relevance), json!(item.id)]
+        } else {
+            fields.iter().map(|field| sort_value(item, field.by)).collect()
+        };
+
+        Cursor { sort: order_by.clone(), keys }.encode()
+    });
+
+    load_tags(&connection, &mut items).await?;
+
+    if tree {
+        load_subtasks(&connection, &mut items).await?;
+    }
+
+    //links to the neighbouring pages with the same filters
+    //cursor pages only link forward
+    let (next, prev) = if cursor.is_some() {
+        (next_cursor.clone().map(|cursor| page_link(&params, count, None, Some(cursor))), None)
+    } else {
+        (
+            has_next.then(|| page_link(&params, count, Some(offset + count), None)),
+            (offset > 0).then(|| page_link(&params, count, Some((offset - count).max(0)), None)),
+        )
+    };
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items,
+        "total": total,
+        "count": count,
+        "offset": if cursor.is_some() { None } else { Some(offset) },
+        "next": next,
+        "prev": prev,
+        "next_cursor": next_cursor
+    })))
+}
+
+/// `FROM` and `WHERE` clause of the todos matching the filters of [`list_todos()`].
+pub(crate) struct TodoFilter {
+    /// clause to append to a `SELECT` of the [`TODO_COLUMNS`]
+    pub sql: String,
+
+    /// values bound to the placeholders of `sql`
+    pub arguments: SqliteArguments<'static>,
+
+    /// `false` if no filter is set and every todo matches
+    pub filtered: bool,
+}
+
+/// Build the clause for the filters of `params`, shared by [`list_todos()`] and [`delete_todos()`](bulk::delete_todos).
+/// 
+/// `search` is the full-text query built by [`match_query()`], the index is joined if it is set.
+pub(crate) fn todo_filter(params: &QueryParams, search: Option<&str>) -> Result<TodoFilter, ApiError> {
+    let mut arguments = SqliteArguments::default();
+    let mut filter = format!("
+        FROM todos {}
         WHERE 1 = 1
-    ");
+    ",
+        if search.is_some() { "JOIN todos_fts ON todos_fts.rowid = todos.id" } else { "" },
+    );
     // https://stackoverflow.com/questions/1264681/what-is-the-purpose-of-using-where-1-1-in-sql-statements
 
+    //set for every condition from the request, the trash condition is always set
+    let mut filtered = false;
+
+    //trashed todos are only listed on request
+    if params.trashed.unwrap_or(false) {
+        filter.push_str(" AND todos.deleted_at IS NOT NULL");
+    } else {
+        filter.push_str(" AND todos.deleted_at IS NULL");
+    }
+
     //append queries
     // search
-    if let Some(search) = &params.search {
-        let query_like = format!("%{}%", search);
-        query.push_str(" AND (title LIKE ? OR content LIKE ?)");
-        let _ = arguments.add(query_like.clone());
-        let _ = arguments.add(query_like);
+    if let Some(search) = search {
+        filter.push_str(" AND todos_fts MATCH ?");
+        let _ = arguments.add(search.to_string());
+        filtered = true;
     }
 
     //filtering
-    if let Some(done) = params.done {
-        query.push_str(" AND done = ? ");
-        let _ = arguments.add(done);
+    match params.done.unwrap_or(DoneFilter::Any) {
+        DoneFilter::Done => {
+            filter.push_str(" AND done = 1");
+            filtered = true;
+        }
+        DoneFilter::Open => {
+            filter.push_str(" AND done = 0");
+            filtered = true;
+        }
+        DoneFilter::Any => {}
     }
 
-    //sorting and finally pagination
-    query.push_str(&format!(
-        " ORDER BY {} {} LIMIT ? OFFSET ?",
-        sort_column, sort_order
-    ));
-    let _ = arguments.add(count);
-    let _ = arguments.add(offset);
+    //overdue = not done and past the due date
+    match params.overdue {
+        None => {}
+        Some(true) => {
+            filter.push_str(" AND done = 0 AND due_date < ?");
+            let _ = arguments.add(now());
+            filtered = true;
+        }
+        Some(false) => {
+            filter.push_str(" AND (done = 1 OR due_date IS NULL OR due_date >= ?)");
+            let _ = arguments.add(now());
+            filtered = true;
+        }
+    }
 
-    let result: Result<Vec<TodoItem>, sqlx::Error> = sqlx::query_as_with::<_, TodoItem, _>(&query, arguments)
-        .fetch_all(&connection)
-        .await;
+    //date ranges, before is exclusive and after is inclusive
+    //NULL never matches
+    let date_ranges = [
+        ("due_date", params.due_after, params.due_before),
+        ("creation_date", params.created_after, params.created_before),
+        ("finish_date", params.finished_after, params.finished_before),
+        ("updated_at", params.updated_since, None),
+    ];
 
-    match result {
-        Ok(items) => {
-            debug!("Handler result: Ok");
-            Json(json!({
-                "status": "ok",
-                "items": items
-            }))
+    for (column, after, before) in date_ranges {
+        if let Some(after) = after {
+            filter.push_str(&format!(" AND {} >= ?", column));
+            let _ = arguments.add(after);
+            filtered = true;
         }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
+        if let Some(before) = before {
+            filter.push_str(&format!(" AND {} < ?", column));
+            let _ = arguments.add(before);
+            filtered = true;
         }
     }
+
+    //priority range, inclusive
+    if let Some(priority_min) = params.priority_min {
+        filter.push_str(" AND priority >= ?");
+        let _ = arguments.add(priority_min);
+        filtered = true;
+    }
+    if let Some(priority_max) = params.priority_max {
+        filter.push_str(" AND priority <= ?");
+        let _ = arguments.add(priority_max);
+        filtered = true;
+    }
+
+    //tags
+    if let Some(tag) = &params.tag {
+        filter.push_str(" AND id IN (
+            SELECT tt.todo_id FROM todo_tags tt JOIN tags t ON t.id = tt.tag_id
+            WHERE t.name = ?
+        )");
+        let _ = arguments.add(tag.trim().to_string());
+        filtered = true;
+    }
+
+    if let Some(any_tag) = &params.any_tag
+        && let tags = split_tags(any_tag)
+        && !tags.is_empty()
+    {
+        filter.push_str(&format!(" AND id IN (
+            SELECT tt.todo_id FROM todo_tags tt JOIN tags t ON t.id = tt.tag_id
+            WHERE t.name IN ({})
+        )", placeholders(tags.len())));
+        for tag in tags {
+            let _ = arguments.add(tag);
+        }
+        filtered = true;
+    }
+
+    if let Some(all_tags) = &params.all_tags
+        && let tags = split_tags(all_tags)
+        && !tags.is_empty()
+    {
+        filter.push_str(&format!(" AND id IN (
+            SELECT tt.todo_id FROM todo_tags tt JOIN tags t ON t.id = tt.tag_id
+            WHERE t.name IN ({})
+            GROUP BY tt.todo_id
+            HAVING COUNT(DISTINCT t.id) = ?
+        )", placeholders(tags.len())));
+        let tag_count = tags.len() as i64;
+        for tag in tags {
+            let _ = arguments.add(tag);
+        }
+        let _ = arguments.add(tag_count);
+        filtered = true;
+    }
+
+    //project
+    match params.project.as_deref().map(str::trim) {
+        None => {}
+        Some("inbox") => {
+            filter.push_str(" AND project_id IS NULL");
+            filtered = true;
+        }
+        Some(project) => {
+            let project_id: i64 = project.parse().map_err(|_| {
+                ApiError::Validation(format!("Invalid project filter: {}, expected a project ID or inbox", project))
+            })?;
+            filter.push_str(" AND project_id = ?");
+            let _ = arguments.add(project_id);
+            filtered = true;
+        }
+    }
+
+    //subtasks
+    //a tree starts at the top-level todos
+    if params.tree.unwrap_or(false) || params.top_level.unwrap_or(false) {
+        filter.push_str(" AND parent_id IS NULL");
+        filtered = true;
+    }
+
+    Ok(TodoFilter { sql: filter, arguments, filtered })
+}
+
+/// Column of a sort field and how its values are bound to a cursor query.
+fn sort_column(by: SortBy) -> (&'static str, KeyKind) {
+    match by {
+        SortBy::CreationDate => ("todos.creation_date", KeyKind::Date),
+        SortBy::DueDate => ("todos.due_date", KeyKind::Date),
+        SortBy::Priority => ("todos.priority", KeyKind::Integer),
+        SortBy::Done => ("todos.done", KeyKind::Integer),
+        SortBy::Title => ("todos.title COLLATE NOCASE", KeyKind::Text),
+        SortBy::FinishDate => ("todos.finish_date", KeyKind::Date),
+        SortBy::UpdatedAt => ("todos.updated_at", KeyKind::Date),
+        SortBy::DeletedAt => ("todos.deleted_at", KeyKind::Date),
+        SortBy::Id => ("todos.id", KeyKind::Integer),
+    }
+}
+
+/// Value of a sort field of a todo as stored in a [`Cursor`].
+fn sort_value(item: &TodoItem, by: SortBy) -> Value {
+    match by {
+        SortBy::CreationDate => json!(item.creation_date.timestamp()),
+        SortBy::DueDate => json!(item.due_date.map(|date| date.timestamp())),
+        SortBy::Priority => json!(item.priority),
+        SortBy::Done => json!(item.done),
+        SortBy::Title => json!(item.title),
+        SortBy::FinishDate => json!(item.finish_date.map(|date| date.timestamp())),
+        SortBy::UpdatedAt => json!(item.updated_at.map(|date| date.timestamp())),
+        SortBy::DeletedAt => json!(item.deleted_at.map(|date| date.timestamp())),
+        SortBy::Id => json!(item.id),
+    }
+}
+
+/// Link to a page of [`list_todos()`] with the same filters and sorting as `params`.
+fn page_link(params: &QueryParams, count: i64, offset: Option<i64>, cursor: Option<String>) -> String {
+    let params = QueryParams {
+        count: Some(count),
+        offset,
+        cursor,
+        ..params.clone()
+    };
+
+    //all values of the query parameters can be encoded
+    format!("/todos?{}", serde_urlencoded::to_string(&params).unwrap_or_default())
 }
 
 /// Returns a specific Todo-item by ID.
 /// 
+/// The `ETag` header holds the version of the todo, send it as `If-Match` when changing or deleting the todo.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists.
+/// 
 /// # Examples
 /// 
 /// Get Todo with ID 42
@@ -120,36 +531,19 @@ pub async fn list_todos(State(connection): State<SqlitePool>, Query(params): Que
 /// curl -X GET http://localhost:3000/todos/42
 /// ```
 // https://docs.rs/axum/latest/axum/extract/struct.Path.html
-pub async fn get_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> impl IntoResponse {
+pub async fn get_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> Result<impl IntoResponse, ApiError> {
     debug!("Called handler get_todo() with ID {:?}", id);
 
-    // get database row for specific ID
-    let result: Result<TodoItem, sqlx::Error> = sqlx::query_as::<_, TodoItem>("
-        SELECT  id, title, content, done, priority,
-                creation_date, due_date, finish_date
-        FROM todos
-        WHERE id = ?
-    ")
-    .bind(id)
-    .fetch_one(&connection)
-    .await;
+    let item = fetch_todo(&connection, id).await?;
 
-    match result {
-        Ok(item) => {
-            debug!("Handler result: Ok");
-            Json(json!({
-                "status": "ok",
-                "item": item
-            }))
-        }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
-        }
-    }
+    debug!("Handler result: Ok");
+    Ok((
+        etag(&item),
+        Json(json!({
+            "status": "ok",
+            "item": item
+        })),
+    ))
 }
 
 /// Update a specific todo item by ID.
@@ -161,81 +555,855 @@ pub async fn get_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>)
 /// # Note
 /// 
 /// Creation date and ID are ignored as they cannot be changed.
+/// The finish date is ignored too, it is set to the time of the server when the todo is marked as done
+/// and removed when it is reopened, like [`toggle_todo()`] does.
+/// The tags of the todo are replaced by the tags in the request body.
+/// With `complete_children=true` all subtasks are marked as done too if the todo is done, see [`UpdateParams`].
+/// 
+/// With an `If-Match` header the todo is only updated if its version matches, see [`get_todo()`].
+/// 
+/// The todo item is returned as it was stored in the database, with its new version as `ETag`.
+/// If a recurring todo is marked as done, the next occurrence is created and returned as `next`.
+/// 
+/// Responds with `400 Bad Request` if the body is not a valid todo item,
+/// with `404 Not Found` if no todo with this ID exists,
+/// with `412 Precondition Failed` if the todo was changed in the meantime
+/// and with `422 Unprocessable Entity` if a field is invalid, see [`validate_todo()`], the project or parent does not exist,
+/// the parent is a subtask of the todo or the recurrence rule is invalid.
 /// 
 /// # Examples
 /// 
 /// Update todo item with ID 10 with the content specified as json.
 /// 
 /// ```bash
-/// curl -X PUT http://localhost:3000/todos/10 -d '{"content":"","creation_date":0,"done":true,"finish_date":0,"due_date":0,"id":0,"priority":0,"title":""}'
+/// curl -X PUT http://localhost:3000/todos/10 -d '{"content":"","creation_date":0,"done":true,"due_date":null,"id":0,"priority":0,"title":""}'
 /// ```
-pub async fn update_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, body: Bytes) -> impl IntoResponse {
-    debug!("Called handler update_todo() with {:?}", body);
+/// 
+/// Update todo item with ID 10 only if it is still at version 3.
+/// 
+/// ```bash
+/// curl -X PUT http://localhost:3000/todos/10 -H 'If-Match: "3"' -d '{"content":"","creation_date":0,"done":false,"due_date":null,"id":0,"priority":0,"title":"mine"}'
+/// ```
+pub async fn update_todo(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Path(id): Path<i64>, Query(params): Query<UpdateParams>, headers: HeaderMap, body: Bytes) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler update_todo() with {:?} and {:?}", params, body);
     
     //try to parse request body
-    let parsed: Result<TodoItem, serde_json::Error> = serde_json::from_slice(&body);
-
-    //handle errors
-    let payload = match parsed {
-        Ok(p) => p,
-        Err(e) => {
-            error!("Invalid JSON: {}", e);
-            return Json(json!({
-                "status": "error",
-                "message": format!("Invalid JSON: {}", e)
-            }));
-        }
-    };
+    let payload: TodoItem = serde_json::from_slice(&body)?;
+
+    let mut transaction = connection.begin().await?;
+    let next_id = replace_todo(&mut transaction, &config, &actor(&headers), id, payload, &params, &if_match(&headers)).await?;
+    transaction.commit().await?;
+
+    let response = item_response(&connection, id, next_id).await?;
 
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Overwrite all fields of a stored todo, the SQL part of [`update_todo()`].
+/// 
+/// Only versions in `versions` are changed, see [`if_match()`].
+/// Returns the ID of the next occurrence if a recurring todo was completed.
+pub(crate) async fn replace_todo(connection: &mut SqliteConnection, config: &Config, actor: &Actor, id: i64, payload: TodoItem, params: &UpdateParams, versions: &Option<Vec<i64>>) -> Result<Option<i64>, ApiError> {
     // run udpate query to database
     // creation_date cannot be changed
-    let result: Result<SqliteQueryResult, sqlx::Error> = sqlx::query("
+    let was_done = was_done(connection, id).await?;
+    let before = snapshot(connection, id).await?;
+    let (creation_date, due_date) = stored_dates(connection, id).await?;
+
+    let mut errors = validate_todo(&payload, creation_date, due_date, config);
+    let recurrence = check_recurrence(&mut errors, payload.recurrence.clone());
+    if let Some(project_id) = payload.project_id {
+        check_project(connection, &mut errors, project_id).await?;
+    }
+    if let Some(parent_id) = payload.parent_id {
+        check_parent(connection, &mut errors, Some(id), parent_id).await?;
+    }
+    into_result(errors)?;
+
+    let query = format!("
         UPDATE todos
-        SET title = ?, content = ?, done = ?, priority = ?, due_date = ?, finish_date = ?
-        WHERE id = ?
-    ")
+        SET title = ?, content = ?, done = ?, priority = ?, due_date = ?, {}, project_id = ?, parent_id = ?, recurrence = ?, updated_at = ?, version = version + 1
+        WHERE id = ? AND deleted_at IS NULL{}
+    ", SET_FINISH_DATE, version_condition(versions));
+
+    let now = now();
+    let mut query = sqlx::query(&query)
     .bind(payload.title)
     .bind(payload.content)
     .bind(payload.done)
     .bind(payload.priority)
     .bind(payload.due_date)
-    .bind(payload.finish_date)
-    .bind(id)
-    .execute(&connection)
-    .await;
-
-    match result {
-        Ok(res) => {
-            //ID does not exits = no row got updated
-            if res.rows_affected() == 0 {
-                error!("Todo with ID {} does not exist", id);
-                return Json(json!({
-                    "status": "error",
-                    "message": format!("Todo with ID {} does not exist", id)
-                }));
+    .bind(payload.done)
+    .bind(now)
+    .bind(payload.project_id)
+    .bind(payload.parent_id)
+    .bind(recurrence)
+    .bind(now)
+    .bind(id);
+
+    for version in versions.iter().flatten() {
+        query = query.bind(version);
+    }
+
+    let result: SqliteQueryResult = query
+        .execute(&mut *connection)
+        .await
+        .map_err(unknown_project)?;
+
+    //ID does not exits or the version does not match = no row got updated
+    if result.rows_affected() == 0 {
+        return Err(not_changed(connection, id).await);
+    }
+
+    set_tags(connection, id, &payload.tags).await?;
+
+    if payload.done && params.complete_children.unwrap_or(false) {
+        complete_children(connection, actor, id).await?;
+    }
+
+    let next_id = if payload.done && !was_done {
+        repeat_todo(connection, actor, id).await?
+    } else {
+        None
+    };
+
+    record(connection, id, Operation::Update, actor, before).await?;
+    Ok(next_id)
+}
+
+/// Partially update a specific todo item by ID.
+/// 
+/// The request body contains a json object with only the fields that should change,
+/// see [`TodoPatch`]. Fields that are not present keep their current value.
+/// The updated todo item is returned.
+/// 
+/// # Note
+/// 
+/// Creation date and ID are ignored as they cannot be changed.
+/// The finish date follows the done status, see [`update_todo()`].
+/// 
+/// With `complete_children=true` all subtasks are marked as done too if the patch marks the todo as done, see [`UpdateParams`].
+/// If a recurring todo is marked as done, the next occurrence is created and returned as `next`.
+/// With an `If-Match` header the todo is only changed if its version matches, see [`get_todo()`].
+/// 
+/// Responds with `400 Bad Request` if the body is not a valid patch,
+/// with `404 Not Found` if no todo with this ID exists,
+/// with `412 Precondition Failed` if the todo was changed in the meantime
+/// and with `422 Unprocessable Entity` if a field is invalid, see [`validate_todo()`], the project or parent does not exist,
+/// the parent is a subtask of the todo or the recurrence rule is invalid.
+/// 
+/// # Examples
+/// 
+/// Mark todo item with ID 10 as done.
+/// 
+/// ```bash
+/// curl -X PATCH http://localhost:3000/todos/10 -d '{"done":true}'
+/// ```
+pub async fn patch_todo(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Path(id): Path<i64>, Query(params): Query<UpdateParams>, headers: HeaderMap, body: Bytes) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler patch_todo() with {:?} and {:?}", params, body);
+
+    //try to parse request body
+    let payload: TodoPatch = serde_json::from_slice(&body)?;
+
+    let mut transaction = connection.begin().await?;
+    let next_id = apply_patch(&mut transaction, &config, &actor(&headers), id, payload, &params, &if_match(&headers)).await?;
+    transaction.commit().await?;
+
+    let response = item_response(&connection, id, next_id).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Write the fields of a partial update, the SQL part of [`patch_todo()`].
+/// 
+/// Only versions in `versions` are changed, see [`if_match()`].
+/// Returns the ID of the next occurrence if a recurring todo was completed.
+pub(crate) async fn apply_patch(connection: &mut SqliteConnection, config: &Config, actor: &Actor, id: i64, payload: TodoPatch, params: &UpdateParams, versions: &Option<Vec<i64>>) -> Result<Option<i64>, ApiError> {
+    let was_done = was_done(connection, id).await?;
+    let before = snapshot(connection, id).await?;
+    let (creation_date, due_date) = stored_dates(connection, id).await?;
+    let now = now();
+
+    let mut errors = validate_patch(&payload, creation_date, due_date, config);
+    let recurrence = payload.recurrence.map(|recurrence| check_recurrence(&mut errors, recurrence));
+    if let Some(Some(project_id)) = payload.project_id {
+        check_project(connection, &mut errors, project_id).await?;
+    }
+    if let Some(Some(parent_id)) = payload.parent_id {
+        check_parent(connection, &mut errors, Some(id), parent_id).await?;
+    }
+    into_result(errors)?;
+
+    //only set the columns present in the request body
+    let mut arguments = SqliteArguments::default();
+    let mut columns: Vec<&str> = Vec::new();
+
+    if let Some(title) = payload.title {
+        columns.push("title = ?");
+        let _ = arguments.add(title);
+    }
+    if let Some(content) = payload.content {
+        columns.push("content = ?");
+        let _ = arguments.add(content);
+    }
+    if let Some(done) = payload.done {
+        columns.push("done = ?");
+        let _ = arguments.add(done);
+        columns.push(SET_FINISH_DATE);
+        let _ = arguments.add(done);
+        let _ = arguments.add(now);
+    }
+    if let Some(priority) = payload.priority {
+        columns.push("priority = ?");
+        let _ = arguments.add(priority);
+    }
+    if let Some(due_date) = payload.due_date {
+        columns.push("due_date = ?");
+        let _ = arguments.add(due_date);
+    }
+    if let Some(project_id) = payload.project_id {
+        columns.push("project_id = ?");
+        let _ = arguments.add(project_id);
+    }
+    if let Some(parent_id) = payload.parent_id {
+        columns.push("parent_id = ?");
+        let _ = arguments.add(parent_id);
+    }
+    if let Some(recurrence) = recurrence {
+        columns.push("recurrence = ?");
+        let _ = arguments.add(recurrence);
+    }
+
+    //every patch counts as change, also if only the tags are replaced
+    columns.push("updated_at = ?");
+    let _ = arguments.add(now);
+    columns.push("version = version + 1");
+
+    let query = format!("
+        UPDATE todos
+        SET {}
+        WHERE id = ? AND deleted_at IS NULL{}
+    ", columns.join(", "), version_condition(versions));
+    let _ = arguments.add(id);
+    for version in versions.iter().flatten() {
+        let _ = arguments.add(*version);
+    }
+
+    let result: SqliteQueryResult = sqlx::query_with(&query, arguments)
+        .execute(&mut *connection)
+        .await
+        .map_err(unknown_project)?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_changed(connection, id).await);
+    }
+
+    if let Some(tags) = payload.tags {
+        set_tags(connection, id, &tags).await?;
+    }
+
+    if payload.done == Some(true) && params.complete_children.unwrap_or(false) {
+        complete_children(connection, actor, id).await?;
+    }
+
+    let next_id = if payload.done == Some(true) && !was_done {
+        repeat_todo(connection, actor, id).await?
+    } else {
+        None
+    };
+
+    record(connection, id, Operation::Update, actor, before).await?;
+    Ok(next_id)
+}
+
+/// Toggle the done status of a specific todo item.
+/// 
+/// A todo that is not done is completed, a todo that is done is reopened, see [`complete_todo()`] and [`reopen_todo()`].
+/// The status is changed in a single query, so two clients toggling at the same time change it twice instead of losing one change.
+/// 
+/// Responds like [`complete_todo()`].
+/// 
+/// # Examples
+/// 
+/// Toggle todo item with ID 10.
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/10/toggle
+/// ```
+pub async fn toggle_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, Query(params): Query<UpdateParams>, headers: HeaderMap) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler toggle_todo() with ID {} and {:?}", id, params);
+
+    let response = set_done(&connection, id, None, &params, &headers).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Mark a specific todo item as done.
+/// 
+/// The finish date is set to the current time of the server, a todo that is already done is not changed.
+/// With `complete_children=true` all subtasks are marked as done too, see [`UpdateParams`].
+/// If a recurring todo is completed, the next occurrence is created and returned as `next`.
+/// With an `If-Match` header the todo is only changed if its version matches, see [`get_todo()`].
+/// 
+/// The todo item is returned with its new version as `ETag`.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists
+/// and with `412 Precondition Failed` if the todo was changed in the meantime.
+/// 
+/// # Examples
+/// 
+/// Complete todo item with ID 10 and all of its subtasks.
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/10/complete?complete_children=true
+/// ```
+pub async fn complete_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, Query(params): Query<UpdateParams>, headers: HeaderMap) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler complete_todo() with ID {} and {:?}", id, params);
+
+    let response = set_done(&connection, id, Some(true), &params, &headers).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Mark a specific todo item as not done.
+/// 
+/// The finish date is removed, a todo that is not done is not changed.
+/// With an `If-Match` header the todo is only changed if its version matches, see [`get_todo()`].
+/// 
+/// The todo item is returned with its new version as `ETag`.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists
+/// and with `412 Precondition Failed` if the todo was changed in the meantime.
+/// 
+/// # Examples
+/// 
+/// Reopen todo item with ID 10.
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/10/reopen
+/// ```
+pub async fn reopen_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler reopen_todo() with ID {}", id);
+
+    let response = set_done(&connection, id, Some(false), &UpdateParams::default(), &headers).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Complete, reopen or toggle a todo for one of the handlers above and build the response.
+async fn set_done(connection: &SqlitePool, id: i64, done: Option<bool>, params: &UpdateParams, headers: &HeaderMap) -> Result<([(HeaderName, String); 1], Json<Value>), ApiError> {
+    let mut transaction = connection.begin().await?;
+    let next_id = change_done(&mut transaction, &actor(headers), id, done, params, &if_match(headers)).await?;
+    transaction.commit().await?;
+
+    item_response(connection, id, next_id).await
+}
+
+/// Change the done status of a todo and set its finish date to the time of the server.
+/// 
+/// `done` is the new status, `None` toggles the status.
+/// Only versions in `versions` are changed, see [`if_match()`].
+/// Returns the ID of the next occurrence if a recurring todo was completed.
+pub(crate) async fn change_done(connection: &mut SqliteConnection, actor: &Actor, id: i64, done: Option<bool>, params: &UpdateParams, versions: &Option<Vec<i64>>) -> Result<Option<i64>, ApiError> {
+    //the new status and finish date are computed from the stored status
+    //todos that already have the requested status are not matched
+    let query = format!("
+        UPDATE todos
+        SET done = NOT done, finish_date = CASE WHEN done THEN NULL ELSE ? END, updated_at = ?, version = version + 1
+        WHERE id = ? AND deleted_at IS NULL{}{}
+        RETURNING done
+    ", if done.is_some() { " AND done = ?" } else { "" }, version_condition(versions));
+
+    let before = snapshot(connection, id).await?;
+    let now = now();
+    let mut query = sqlx::query_scalar(&query)
+        .bind(now)
+        .bind(now)
+        .bind(id);
+
+    if let Some(done) = done {
+        query = query.bind(!done);
+    }
+    for version in versions.iter().flatten() {
+        query = query.bind(version);
+    }
+
+    let changed: Option<bool> = query
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    let next_id = match changed {
+        Some(true) => {
+            if params.complete_children.unwrap_or(false) {
+                complete_children(connection, actor, id).await?;
+            }
+            repeat_todo(connection, actor, id).await?
+        }
+        Some(false) => None,
+        None => {
+            //no row changed, the todo might already have the requested status
+            let query = format!("SELECT id FROM todos WHERE id = ? AND deleted_at IS NULL{}", version_condition(versions));
+            let mut query = sqlx::query_scalar(&query).bind(id);
+            for version in versions.iter().flatten() {
+                query = query.bind(version);
             }
 
-            debug!("Handler result: Ok");
-            Json(json!({ "status": "ok" }))
+            let unchanged: Option<i64> = query
+                .fetch_optional(&mut *connection)
+                .await?;
+
+            if unchanged.is_none() || done.is_none() {
+                return Err(not_changed(connection, id).await);
+            }
+            return Ok(None);
         }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
+    };
+
+    record(connection, id, Operation::Toggle, actor, before).await?;
+    Ok(next_id)
+}
+
+/// Returns the direct subtasks of a specific todo item, oldest first.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists.
+/// 
+/// # Examples
+/// 
+/// Get the subtasks of todo 42
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos/42/children
+/// ```
+pub async fn list_children(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_children() with ID {}", id);
+
+    //make sure the parent exists
+    fetch_todo(&connection, id).await?;
+
+    let mut items: Vec<TodoItem> = sqlx::query_as::<_, TodoItem>(&format!("
+        SELECT {}
+        FROM todos
+        WHERE parent_id = ? AND deleted_at IS NULL
+        ORDER BY creation_date, id
+    ", TODO_COLUMNS))
+    .bind(id)
+    .fetch_all(&connection)
+    .await?;
+    load_tags(&connection, &mut items).await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
+}
+
+/// Check that `parent_id` can be the parent of the todo `id`, an invalid parent is added to `errors`.
+/// 
+/// The parent must exist and must not be the todo itself or one of its subtasks,
+/// otherwise the subtasks would form a cycle. Use `None` for a todo that is not created yet.
+async fn check_parent(connection: &mut SqliteConnection, errors: &mut Vec<FieldError>, id: Option<i64>, parent_id: i64) -> Result<(), sqlx::Error> {
+    let exists = sqlx::query("SELECT id FROM todos WHERE id = ? AND deleted_at IS NULL")
+        .bind(parent_id)
+        .fetch_optional(&mut *connection)
+        .await?
+        .is_some();
+
+    if !exists {
+        push(errors, "parent_id", format!("todo with ID {} does not exist", parent_id));
+        return Ok(());
+    }
+
+    let Some(id) = id else {
+        return Ok(());
+    };
+
+    //walk up from the new parent, the todo must not be one of its ancestors
+    let cycle = sqlx::query("
+        WITH RECURSIVE ancestors(id) AS (
+            SELECT ?
+            UNION
+            SELECT t.parent_id FROM todos t JOIN ancestors a ON t.id = a.id
+            WHERE t.parent_id IS NOT NULL
+        )
+        SELECT id FROM ancestors WHERE id = ?
+    ")
+    .bind(parent_id)
+    .bind(id)
+    .fetch_optional(&mut *connection)
+    .await?
+    .is_some();
+
+    if cycle {
+        push(errors, "parent_id", "must not be the todo itself or one of its subtasks".to_string());
+    }
+
+    Ok(())
+}
+
+/// Mark all subtasks of a todo as done, including subtasks of subtasks.
+/// 
+/// Subtasks get the finish date of the todo, subtasks that are already done are not changed.
+/// Recurring subtasks get their next occurrence, see [`repeat_todo()`].
+async fn complete_children(connection: &mut SqliteConnection, actor: &Actor, id: i64) -> Result<(), ApiError> {
+    let ids = descendants(connection, id).await?;
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let before = snapshots(connection, &ids).await?;
+
+    let query = format!("
+        UPDATE todos
+        SET done = 1, finish_date = (SELECT finish_date FROM todos WHERE id = ?), updated_at = ?, version = version + 1
+        WHERE id IN ({}) AND done = 0
+    ", placeholders(ids.len()));
+
+    let mut query = sqlx::query(&query)
+        .bind(id)
+        .bind(now());
+    for id in &ids {
+        query = query.bind(id);
+    }
+    query.execute(&mut *connection).await?;
+
+    //completed recurring subtasks repeat like the todo itself
+    let repeating: Vec<i64> = before
+        .iter()
+        .filter(|(_, snapshot)| snapshot.as_ref().is_some_and(|snapshot| !snapshot.done && snapshot.recurrence.is_some()))
+        .map(|(id, _)| *id)
+        .collect();
+    for child_id in repeating {
+        repeat_todo(connection, actor, child_id).await?;
+    }
+
+    //subtasks that were already done are not recorded
+    Ok(record_all(connection, before, Operation::Toggle, actor).await?)
+}
+
+/// IDs of all subtasks of a todo that are not in the trash, including subtasks of subtasks.
+async fn descendants(connection: &mut SqliteConnection, id: i64) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("
+        WITH RECURSIVE descendants(id) AS (
+            SELECT id FROM todos WHERE parent_id = ? AND deleted_at IS NULL
+            UNION
+            SELECT t.id FROM todos t JOIN descendants d ON t.parent_id = d.id
+            WHERE t.deleted_at IS NULL
+        )
+        SELECT id FROM descendants
+    ")
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await
+}
+
+/// Load all subtasks of the given todo items and nest them in `children`.
+async fn load_subtasks(connection: &SqlitePool, items: &mut [TodoItem]) -> Result<(), ApiError> {
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let mut arguments = SqliteArguments::default();
+    for item in items.iter() {
+        let _ = arguments.add(item.id);
+    }
+
+    let query = format!("
+        WITH RECURSIVE descendants(id) AS (
+            SELECT id FROM todos WHERE parent_id IN ({})
+            UNION
+            SELECT t.id FROM todos t JOIN descendants d ON t.parent_id = d.id
+        )
+        SELECT {}
+        FROM todos
+        WHERE id IN (SELECT id FROM descendants) AND deleted_at IS NULL
+        ORDER BY creation_date, id
+    ", placeholders(items.len()), TODO_COLUMNS);
+
+    let mut subtasks: Vec<TodoItem> = sqlx::query_as_with::<_, TodoItem, _>(&query, arguments)
+        .fetch_all(connection)
+        .await?;
+    load_tags(connection, &mut subtasks).await?;
+
+    //group subtasks by parent, keeps the order of the query
+    let mut by_parent: HashMap<i64, Vec<TodoItem>> = HashMap::new();
+    for subtask in subtasks {
+        if let Some(parent_id) = subtask.parent_id {
+            by_parent.entry(parent_id).or_default().push(subtask);
         }
     }
+
+    for item in items.iter_mut() {
+        nest_children(item, &mut by_parent);
+    }
+
+    Ok(())
+}
+
+/// Move the subtasks of `item` from `by_parent` into `item.children`, recursively.
+fn nest_children(item: &mut TodoItem, by_parent: &mut HashMap<i64, Vec<TodoItem>>) {
+    item.children = by_parent.remove(&item.id).unwrap_or_default();
+
+    for child in item.children.iter_mut() {
+        nest_children(child, by_parent);
+    }
+}
+
+/// Preview the next occurrences of a recurring todo item after its due date.
+/// 
+/// The occurrences are returned as epoch seconds, `count` defaults to 5, see [`OccurrenceParams`].
+/// A todo without recurrence rule has no occurrences.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists.
+/// 
+/// # Examples
+/// 
+/// Get the next 10 due dates of todo 42
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/todos/42/occurrences?count=10
+/// ```
+pub async fn list_occurrences(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Path(id): Path<i64>, Query(params): Query<OccurrenceParams>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_occurrences() with ID {} and {:?}", id, params);
+
+    let item = fetch_todo(&connection, id).await?;
+    let count = params.count.unwrap_or(5).clamp(1, config.max_page_size);
+
+    let items: Vec<i64> = match &item.recurrence {
+        Some(rule) => next_occurrences(rule, repeat_start(item.due_date), count as usize)?
+            .iter()
+            .map(DateTime::timestamp)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
+}
+
+/// Done status of a todo before it is changed.
+/// 
+/// Returns [`ApiError::NotFound`] if no todo with this ID exists.
+async fn was_done(connection: &mut SqliteConnection, id: i64) -> Result<bool, ApiError> {
+    sqlx::query_scalar("SELECT done FROM todos WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))
+}
+
+/// Create the next occurrence of a recurring todo that was just marked as done.
+/// 
+/// The copy keeps title, content, priority, project, parent and tags, its due date is advanced by the rule.
+/// The rule moves on to the copy, so completing the todo again does not create a second occurrence.
+/// Call it after the update that completed the todo and before recording it, the removed rule is part of that change.
+/// Returns the ID of the new todo or `None` if the todo does not repeat or the series has ended.
+async fn repeat_todo(connection: &mut SqliteConnection, actor: &Actor, id: i64) -> Result<Option<i64>, ApiError> {
+    let (rule, due_date): (Option<String>, Option<DateTime<Utc>>) = sqlx::query_as("SELECT recurrence, due_date FROM todos WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *connection)
+        .await?;
+
+    let Some(rule) = rule else {
+        return Ok(None);
+    };
+
+    let Some((next_due_date, next_rule)) = next_occurrence(&rule, repeat_start(due_date))? else {
+        return Ok(None);
+    };
+
+    let next_id: i64 = sqlx::query_scalar("
+        INSERT INTO todos (title, content, done, priority, due_date, finish_date, creation_date, updated_at, project_id, parent_id, recurrence)
+        SELECT title, content, 0, priority, ?, ?, ?, ?, project_id, parent_id, ?
+        FROM todos
+        WHERE id = ?
+        RETURNING id
+    ")
+    .bind(next_due_date)
+    .bind(None::<DateTime<Utc>>)
+    .bind(now())
+    .bind(now())
+    .bind(next_rule)
+    .bind(id)
+    .fetch_one(&mut *connection)
+    .await?;
+
+    sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) SELECT ?, tag_id FROM todo_tags WHERE todo_id = ?")
+        .bind(next_id)
+        .bind(id)
+        .execute(&mut *connection)
+        .await?;
+
+    record(connection, next_id, Operation::Create, actor, None).await?;
+
+    //part of the change that completed the todo, the caller increments the version and records it
+    sqlx::query("UPDATE todos SET recurrence = NULL WHERE id = ?")
+        .bind(id)
+        .execute(&mut *connection)
+        .await?;
+
+    debug!("Created occurrence {} of recurring todo {}", next_id, id);
+    Ok(Some(next_id))
+}
+
+/// First occurrence of a recurrence rule, todos without due date repeat from now.
+fn repeat_start(due_date: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    due_date.unwrap_or_else(now)
+}
+
+/// Creation and due date of a todo, responds with [`ApiError::NotFound`] if no todo with this ID exists.
+async fn stored_dates(connection: &mut SqliteConnection, id: i64) -> Result<(DateTime<Utc>, Option<DateTime<Utc>>), ApiError> {
+    sqlx::query_as("SELECT creation_date, due_date FROM todos WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))
+}
+
+/// Get the database row of a specific todo item by ID.
+/// 
+/// Returns [`ApiError::NotFound`] if no todo with this ID exists or the todo is in the trash.
+pub(crate) async fn fetch_todo(connection: &SqlitePool, id: i64) -> Result<TodoItem, ApiError> {
+    let item: TodoItem = sqlx::query_as::<_, TodoItem>(&format!("
+        SELECT {}
+        FROM todos
+        WHERE id = ? AND deleted_at IS NULL
+    ", TODO_COLUMNS))
+    .bind(id)
+    .fetch_optional(connection)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))?;
+
+    let mut items = [item];
+    load_tags(connection, &mut items).await?;
+
+    let [item] = items;
+    Ok(item)
+}
+
+/// Get the database rows of several todo items by ID, including todos in the trash.
+/// 
+/// IDs of purged todos are left out, the items are ordered by ID.
+pub(crate) async fn fetch_including_trashed(connection: &SqlitePool, ids: &[i64]) -> Result<Vec<TodoItem>, ApiError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let query = format!("
+        SELECT {}
+        FROM todos
+        WHERE id IN ({})
+        ORDER BY id
+    ", TODO_COLUMNS, placeholders(ids.len()));
+
+    let mut query = sqlx::query_as::<_, TodoItem>(&query);
+    for id in ids {
+        query = query.bind(id);
+    }
+    let mut items = query.fetch_all(connection).await?;
+
+    load_tags(connection, &mut items).await?;
+    Ok(items)
+}
+
+/// Response with a changed todo, its version as `ETag` and the next occurrence as `next` if one was created.
+pub(crate) async fn item_response(connection: &SqlitePool, id: i64, next_id: Option<i64>) -> Result<([(HeaderName, String); 1], Json<Value>), ApiError> {
+    let item = fetch_todo(connection, id).await?;
+    let etag = etag(&item);
+    let mut response = json!({
+        "status": "ok",
+        "item": item
+    });
+
+    if let Some(next_id) = next_id {
+        response["next"] = json!(fetch_todo(connection, next_id).await?);
+    }
+
+    Ok((etag, Json(response)))
+}
+
+/// `ETag` header with the version of a todo item.
+fn etag(item: &TodoItem) -> [(HeaderName, String); 1] {
+    [(header::ETAG, format!("\"{}\"", item.version))]
+}
+
+/// Versions of a todo the `If-Match` header of a request accepts.
+/// 
+/// Returns `None` if the header is missing or `*`, then every version is accepted.
+/// Tags that are not the `ETag` of a todo never match.
+pub(crate) fn if_match(headers: &HeaderMap) -> Option<Vec<i64>> {
+    let tags: Vec<&str> = headers
+        .get_all(header::IF_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    if tags.is_empty() || tags.contains(&"*") {
+        return None;
+    }
+
+    Some(tags
+        .iter()
+        .filter_map(|tag| tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+        .collect())
+}
+
+/// Condition that limits a change to the versions accepted by `If-Match`, bind the versions after it.
+fn version_condition(versions: &Option<Vec<i64>>) -> String {
+    match versions {
+        Some(versions) => format!(" AND version IN ({})", placeholders(versions.len())),
+        None => String::new(),
+    }
+}
+
+/// Error for a change that matched no todo, either the todo does not exist or `If-Match` does not match its version.
+async fn not_changed(connection: &mut SqliteConnection, id: i64) -> ApiError {
+    let version: Result<Option<i64>, sqlx::Error> = sqlx::query_scalar("SELECT version FROM todos WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await;
+
+    match version {
+        Ok(Some(version)) => ApiError::PreconditionFailed(format!("Todo with ID {} was changed in the meantime, current version is {}", id, version)),
+        Ok(None) => ApiError::NotFound(format!("Todo with ID {} does not exist", id)),
+        Err(e) => ApiError::Database(e),
+    }
+}
+
+/// Current time in whole seconds.
+/// 
+/// Dates are sent as epoch seconds, so stored dates have no fraction either and compare equal to the dates in a [`Cursor`].
+pub(crate) fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0)
 }
 
-/// Return todo items which title or content includes the query string.
+/// Comma separated `?` placeholders for an `IN (...)` list.
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// Return the 10 todo items that match the query string best.
+/// 
+/// Uses the full-text index like the `search` of [`list_todos()`], the last word matches as prefix.
+/// Words only match from their start, `1` finds `10` but not `Test1`.
 /// 
 /// # Examples
 /// ```bash
 /// curl -X GET http://localhost:3000/todos/autocomplete?q=Hello,%20World!
 /// ```
 #[deprecated(since="1.0.0", note="please use `list_todos` instead")]
-pub async fn autocomplete_todos(State(connection): State<SqlitePool>, Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+pub async fn autocomplete_todos(State(connection): State<SqlitePool>, Query(params): Query<HashMap<String, String>>) -> Result<Json<Value>, ApiError> {
     debug!("Called handler autocomplete_todos() with {:?}", params);
 
     //get the query based on the key "q"
@@ -243,47 +1411,47 @@ pub async fn autocomplete_todos(State(connection): State<SqlitePool>, Query(para
     let query = match params.get("q") {
         Some(q) if !q.trim().is_empty() => q,
         _ => {
-            return Json(json!({
+            return Ok(Json(json!({
                 "status": "ok",
                 "items": []
-            }));
+            })));
         }
     };
 
-    //prepares string so the sql query "like" works
-    //% in front and back means anything can be in front and back
-    let query_like = format!("%{}%", query);
+    //query without words, eg only quotes
+    let Some(query) = autocomplete_query(query) else {
+        return Ok(Json(json!({
+            "status": "ok",
+            "items": []
+        })));
+    };
 
-    let result = sqlx::query_as::<_, TodoItem>("
-        SELECT *
-        FROM todos
-        WHERE title LIKE ? OR content LIKE ?
+    let mut items: Vec<TodoItem> = sqlx::query_as::<_, TodoItem>(&format!("
+        SELECT {}
+        FROM todos JOIN todos_fts ON todos_fts.rowid = todos.id
+        WHERE todos_fts MATCH ? AND todos.deleted_at IS NULL
+        ORDER BY {}, todos.id
         LIMIT 10
-    ")
-    .bind(&query_like)
-    .bind(&query_like)
+    ", TODO_COLUMNS, RANK))
+    .bind(query)
     .fetch_all(&connection)
-    .await;
+    .await?;
+    load_tags(&connection, &mut items).await?;
 
-    match result {
-        Ok(items) => {
-            debug!("Handler result: Ok");
-            Json(json!({
-                "status": "ok",
-                "items": items
-            }))
-        }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
-        }
-    }
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
 }
 
-/// Delete a specific todo item based on ID from the database
+/// Move a specific todo item based on ID to the trash
+/// 
+/// The todo and its subtasks are hidden from the list and can be restored until they are purged, see [`trash`].
+/// With an `If-Match` header the todo is only deleted if its version matches, see [`get_todo()`].
+/// 
+/// Responds with `404 Not Found` if no todo with this ID exists or it is already in the trash
+/// and with `412 Precondition Failed` if the todo was changed in the meantime.
 /// 
 /// # Examples
 /// 
@@ -292,39 +1460,72 @@ pub async fn autocomplete_todos(State(connection): State<SqlitePool>, Query(para
 /// ```bash
 /// curl -X DELETE http://localhost:3000/todos/42
 /// ```
-pub async fn delete_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> impl IntoResponse {
+pub async fn delete_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
     debug!("Called handler delete_todo() with ID {}", id);
 
-    //delete row from database
-    let result: Result<SqliteQueryResult, sqlx::Error> = sqlx::query("
-        DELETE FROM todos WHERE id = ?
-    ")
-    .bind(id)
-    .execute(&connection)
-    .await;
-
-    match result {
-        Ok(res) => {
-            //ID does not exits = no row got updated
-            if res.rows_affected() == 0 {
-                error!("Todo with ID {} does not exist", id);
-                return Json(json!({
-                    "status": "error",
-                    "message": format!("Todo with ID {} does not exist", id)
-                }));
-            }
+    let mut transaction = connection.begin().await?;
+    remove_todo(&mut transaction, &actor(&headers), id, &if_match(&headers)).await?;
+    transaction.commit().await?;
 
-            debug!("Handler result: Ok");
-            Json(json!({ "status": "ok" }))
-        }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
-        }
+    debug!("Handler result: Ok");
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// Move a todo and its subtasks to the trash, the SQL part of [`delete_todo()`].
+/// 
+/// Only versions in `versions` are deleted, see [`if_match()`].
+pub(crate) async fn remove_todo(connection: &mut SqliteConnection, actor: &Actor, id: i64, versions: &Option<Vec<i64>>) -> Result<(), ApiError> {
+    let query = format!("
+        UPDATE todos
+        SET deleted_at = ?, updated_at = ?, version = version + 1
+        WHERE id = ? AND deleted_at IS NULL{}
+    ", version_condition(versions));
+
+    let before = snapshot(connection, id).await?;
+    let now = now();
+    let mut query = sqlx::query(&query)
+        .bind(now)
+        .bind(now)
+        .bind(id);
+    for version in versions.iter().flatten() {
+        query = query.bind(version);
     }
+
+    let result: SqliteQueryResult = query
+        .execute(&mut *connection)
+        .await?;
+
+    //ID does not exits or the version does not match = no row got deleted
+    if result.rows_affected() == 0 {
+        return Err(not_changed(connection, id).await);
+    }
+
+    record(connection, id, Operation::Delete, actor, before).await?;
+
+    //subtasks get the same time, so they are restored together with the todo
+    let ids = descendants(connection, id).await?;
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let before = snapshots(connection, &ids).await?;
+
+    let query = format!("
+        UPDATE todos
+        SET deleted_at = ?, updated_at = ?, version = version + 1
+        WHERE id IN ({})
+    ", placeholders(ids.len()));
+
+    let mut query = sqlx::query(&query)
+        .bind(now)
+        .bind(now);
+    for id in &ids {
+        query = query.bind(id);
+    }
+    query.execute(&mut *connection).await?;
+
+    record_all(connection, before, Operation::Delete, actor).await?;
+    Ok(())
 }
 
 /// Create a Todo item and add to the DB.
@@ -336,59 +1537,87 @@ pub async fn delete_todo(State(connection): State<SqlitePool>, Path(id): Path<i6
 /// # Note
 /// 
 /// ID is ignored as it is generated by database.
+/// Creation date and update time are ignored as they are set to the current time of the server,
+/// so is the finish date of a todo that is created as done.
+/// 
+/// Responds with `201 Created`, a `Location` header pointing to the new todo item
+/// and the todo item as it was stored in the database, including the generated ID.
+/// 
+/// Responds with `400 Bad Request` if the body is not a valid todo item
+/// and with `422 Unprocessable Entity` if a field is invalid, see [`validate_todo()`], the project or parent does not exist
+/// or the recurrence rule is invalid.
 /// 
 /// # Examples
 /// 
 /// Create a new todo item based on the following json:
 /// 
 /// ```bash
-/// curl -X POST http://localhost:3000/todos -d '{"content":"sample","creation_date":1,"done":false,"finish_date":0,"due_date":0,"id":0,"priority":0,"title":"some title"}'
+/// curl -X POST http://localhost:3000/todos -d '{"content":"sample","done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"some title"}'
 /// ```
-pub async fn add_todo(State(connection): State<SqlitePool>, body: Bytes) -> impl IntoResponse {
+pub async fn add_todo(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, headers: HeaderMap, body: Bytes) -> Result<impl IntoResponse, ApiError> {
     debug!("Called handler add_todo() with {:?}", body);
     
     //try to parse request body
-    let parsed: Result<TodoItem, serde_json::Error> = serde_json::from_slice(&body);
-
-    //handle errors
-    let payload = match parsed {
-        Ok(p) => p,
-        Err(e) => {
-            error!("Invalid JSON: {}", e);
-            return Json(json!({
-                "status": "error",
-                "message": format!("Invalid JSON: {}", e)
-            }));
-        }
-    };
+    let payload: TodoItem = serde_json::from_slice(&body)?;
 
-    // run udpate query to database
-    // creation_date cannot be changed
-    let result: Result<SqliteQueryResult, sqlx::Error> = sqlx::query("
-        INSERT INTO todos (title, content, done, priority, due_date, finish_date, creation_date)
-        VALUES (?, ?, ?, ?, ?, ?, ?)
+    let mut transaction = connection.begin().await?;
+    let id = insert_todo(&mut transaction, &config, &actor(&headers), payload).await?;
+    transaction.commit().await?;
+
+    let item = fetch_todo(&connection, id).await?;
+
+    debug!("Handler result: Ok, inserted new todo with ID {}", item.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/todos/{}", item.id))],
+        etag(&item),
+        Json(json!({
+            "status": "ok",
+            "item": item
+        })),
+    ))
+}
+
+/// Store a new todo, the SQL part of [`add_todo()`].
+/// 
+/// Returns the ID generated by the database.
+pub(crate) async fn insert_todo(connection: &mut SqliteConnection, config: &Config, actor: &Actor, payload: TodoItem) -> Result<i64, ApiError> {
+    // run insert query to database
+    // the ID is generated by the database, the dates by the server
+    let now = now();
+
+    let mut errors = validate_todo(&payload, now, None, config);
+    let recurrence = check_recurrence(&mut errors, payload.recurrence.clone());
+    if let Some(project_id) = payload.project_id {
+        check_project(connection, &mut errors, project_id).await?;
+    }
+    if let Some(parent_id) = payload.parent_id {
+        check_parent(connection, &mut errors, None, parent_id).await?;
+    }
+    into_result(errors)?;
+
+    let id: i64 = sqlx::query_scalar("
+        INSERT INTO todos (title, content, done, priority, due_date, finish_date, creation_date, updated_at, project_id, parent_id, recurrence)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id
     ")
     .bind(payload.title)
     .bind(payload.content)
     .bind(payload.done)
     .bind(payload.priority)
     .bind(payload.due_date)
-    .bind(payload.finish_date)
-    .bind(payload.creation_date)
-    .execute(&connection)
-    .await;
-
-    match result {
-        Ok(res) => {
-            debug!("Handler result: Ok, inserted new todo with ID {}", res.last_insert_rowid());
-            Json(json!({ "status": "ok" }))
-        }
-        Err(e) => {
-            error!("Handler result: {:?}", e);
-            Json(json!({
-                "status": "error",
-                "message": e.to_string()
-            }))
-        }
-    }
-}
\ No newline at end of file
+    .bind(payload.done.then_some(now))
+    .bind(now)
+    .bind(now)
+    .bind(payload.project_id)
+    .bind(payload.parent_id)
+    .bind(recurrence)
+    .fetch_one(&mut *connection)
+    .await
+    .map_err(unknown_project)?;
+
+    set_tags(connection, id, &payload.tags).await?;
+    record(connection, id, Operation::Create, actor, None).await?;
+
+    Ok(id)
+}
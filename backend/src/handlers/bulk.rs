@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use axum::{Json, body::Bytes, extract::{Query, State}, http::HeaderMap};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::sqlite::SqlitePool;
+
+use crate::config::Config;
+use crate::data_structs::{BulkOperation, BulkRequest, Operation, QueryParams, UpdateParams};
+use crate::error::ApiError;
+use crate::handlers::{TodoFilter, apply_patch, change_done, fetch_todo, insert_todo, now, placeholders, remove_todo, todo_filter};
+use crate::history::{actor, record_all, snapshots};
+use crate::search::match_query;
+
+/// Apply several operations to todo items in one transaction.
+///
+/// The request body contains a list of `operations`, each selected by its `op` field:
+///
+/// - `create` with an `item` like [`add_todo()`](crate::handlers::add_todo)
+/// - `update` with an `id` and a `patch` like [`patch_todo()`](crate::handlers::patch_todo)
+/// - `delete` with an `id` like [`delete_todo()`](crate::handlers::delete_todo), the todo is moved to the trash
+/// - `toggle` with an `id` like [`toggle_todo()`](crate::handlers::toggle_todo)
+///
+/// `update`, `delete` and `toggle` take an optional `version`, the operation fails if the todo has another version.
+/// The operations are applied in order, either all of them are saved or none.
+///
+/// Responds with a result for every operation in `results`, in the order of the request.
+/// Each result contains the `op`, the `id` of the todo and, unless the todo was deleted, the `item` as it is after all operations.
+/// A completed recurring todo also contains its `next` occurrence.
+///
+/// Responds with the status code of the first failed operation and its position in `index`, eg `404 Not Found` if a todo does not exist.
+/// Responds with `422 Unprocessable Entity` if the request contains more than `max_bulk_operations` operations.
+///
+/// # Examples
+///
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/bulk -d '{"operations":[
+///     {"op":"create","item":{"id":0,"title":"Write release notes","content":"","done":false,"priority":1,"tags":[]}},
+///     {"op":"update","id":3,"patch":{"priority":2},"version":4},
+///     {"op":"toggle","id":5},
+///     {"op":"delete","id":7}
+/// ]}'
+/// ```
+pub async fn bulk_todos(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, headers: HeaderMap, body: Bytes) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler bulk_todos() with {:?}", body);
+
+    //try to parse request body
+    let payload: BulkRequest = serde_json::from_slice(&body)?;
+
+    if payload.operations.len() > config.max_bulk_operations {
+        return Err(ApiError::Validation(format!(
+            "Too many operations: {}, at most {} are allowed per request",
+            payload.operations.len(),
+            config.max_bulk_operations,
+        )));
+    }
+
+    let actor = actor(&headers);
+
+    //op, ID and next occurrence of every operation
+    let mut applied: Vec<(&str, i64, Option<i64>)> = Vec::new();
+
+    //the transaction is rolled back when it is dropped on error
+    let mut transaction = connection.begin().await?;
+
+    for (index, operation) in payload.operations.into_iter().enumerate() {
+        let result = match operation {
+            BulkOperation::Create { item } => {
+                insert_todo(&mut transaction, &config, &actor, item)
+                    .await
+                    .map(|id| ("create", id, None))
+            }
+            BulkOperation::Update { id, patch, version } => {
+                apply_patch(&mut transaction, &config, &actor, id, patch, &UpdateParams::default(), &version.map(|version| vec![version]))
+                    .await
+                    .map(|next_id| ("update", id, next_id))
+            }
+            BulkOperation::Delete { id, version } => {
+                remove_todo(&mut transaction, &actor, id, &version.map(|version| vec![version]))
+                    .await
+                    .map(|_| ("delete", id, None))
+            }
+            BulkOperation::Toggle { id, version } => {
+                change_done(&mut transaction, &actor, id, None, &UpdateParams::default(), &version.map(|version| vec![version]))
+                    .await
+                    .map(|next_id| ("toggle", id, next_id))
+            }
+        };
+
+        applied.push(result.map_err(|e| ApiError::Bulk(index, Box::new(e)))?);
+    }
+
+    transaction.commit().await?;
+
+    //todos are loaded after all operations, a later operation might have deleted them
+    let mut results = Vec::new();
+    for (op, id, next_id) in applied {
+        let mut result = json!({
+            "op": op,
+            "id": id
+        });
+
+        if op != "delete" {
+            result["item"] = json!(fetch_existing(&connection, id).await?);
+        }
+        if let Some(next_id) = next_id {
+            result["next"] = json!(fetch_existing(&connection, next_id).await?);
+        }
+
+        results.push(result);
+    }
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "results": results
+    })))
+}
+
+/// Move all todo items matching the filters of [`list_todos()`](crate::handlers::list_todos) to the trash.
+///
+/// Takes the same filters as the list, sorting and pagination are ignored. Subtasks of deleted todos are moved to the trash too.
+///
+/// Responds with the number of deleted todos in `deleted`, including subtasks.
+/// Responds with `422 Unprocessable Entity` if no filter is given, so a request cannot delete every todo by accident,
+/// or if `trashed` is set, use [`purge_trash()`](crate::handlers::trash::purge_trash) for todos in the trash.
+///
+/// # Examples
+///
+/// Delete all todo items that are done:
+///
+/// ```bash
+/// curl -X DELETE http://localhost:3000/todos?done=true
+/// ```
+///
+/// Delete the done todo items of project `3` tagged with `draft`:
+///
+/// ```bash
+/// curl -X DELETE http://localhost:3000/todos?done=true&project=3&tag=draft
+/// ```
+pub async fn delete_todos(State(connection): State<SqlitePool>, Query(params): Query<QueryParams>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler delete_todos() with {:?}", params);
+
+    if params.trashed.unwrap_or(false) {
+        return Err(ApiError::Validation("Todos in the trash cannot be deleted again, purge the trash instead".to_string()));
+    }
+
+    let search = params.search.as_deref().and_then(match_query);
+    let TodoFilter { sql, arguments, filtered } = todo_filter(&params, search.as_deref())?;
+
+    if !filtered {
+        return Err(ApiError::Validation("Deleting todos requires at least one filter".to_string()));
+    }
+
+    //subtasks get the same time, so they are restored together with their todo
+    let query = format!("
+        WITH RECURSIVE trashed(id) AS (
+            SELECT todos.id {}
+            UNION
+            SELECT t.id FROM todos t JOIN trashed d ON t.parent_id = d.id
+            WHERE t.deleted_at IS NULL
+        )
+        SELECT id FROM trashed
+    ", sql);
+
+    let mut transaction = connection.begin().await?;
+
+    let ids: Vec<i64> = sqlx::query_scalar_with(&query, arguments)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    if !ids.is_empty() {
+        let before = snapshots(&mut transaction, &ids).await?;
+
+        let query = format!("
+            UPDATE todos
+            SET deleted_at = ?, updated_at = ?, version = version + 1
+            WHERE id IN ({})
+        ", placeholders(ids.len()));
+
+        let now = now();
+        let mut query = sqlx::query(&query)
+            .bind(now)
+            .bind(now);
+        for id in &ids {
+            query = query.bind(id);
+        }
+        query.execute(&mut *transaction).await?;
+
+        record_all(&mut transaction, before, Operation::Delete, &actor(&headers)).await?;
+    }
+
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok, deleted {} todos", ids.len());
+    Ok(Json(json!({
+        "status": "ok",
+        "deleted": ids.len()
+    })))
+}
+
+/// Load a todo for a result of [`bulk_todos()`], `None` if it was deleted by a later operation.
+async fn fetch_existing(connection: &SqlitePool, id: i64) -> Result<Option<Value>, ApiError> {
+    match fetch_todo(connection, id).await {
+        Ok(item) => Ok(Some(json!(item))),
+        Err(ApiError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
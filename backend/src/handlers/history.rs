@@ -0,0 +1,409 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{Json, extract::{Path, Query, State}, http::HeaderMap, response::IntoResponse};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::{Arguments, SqliteConnection};
+use sqlx::sqlite::{SqliteArguments, SqlitePool};
+
+use crate::config::Config;
+use crate::data_structs::{EventParams, Operation, RevertParams, TodoEvent};
+use crate::error::ApiError;
+use crate::handlers::{check_parent, descendants, fetch_todo, if_match, item_response, not_changed, now};
+use crate::handlers::projects::unknown_project;
+use crate::handlers::tags::set_tags;
+use crate::history::{EVENT_COLUMNS, EventRow, TodoSnapshot, actor, record, snapshot};
+use crate::validation::into_result;
+
+/// Returns the change history of a specific todo item, oldest change first.
+///
+/// Every event contains the time, the actor from the `X-Actor` header of the request, the operation
+/// and the changed fields with their values `before` and `after` the change, see [`TodoEvent`].
+/// The history is kept for todos in the trash and after they are purged.
+///
+/// Responds with `404 Not Found` if no todo with this ID exists and none ever did.
+///
+/// # Examples
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/todos/42/history
+/// ```
+pub async fn todo_history(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler todo_history() with ID {}", id);
+
+    let rows: Vec<EventRow> = sqlx::query_as(&format!("
+        SELECT {}
+        FROM todo_events
+        WHERE todo_id = ?
+        ORDER BY id
+    ", EVENT_COLUMNS))
+    .bind(id)
+    .fetch_all(&connection)
+    .await?;
+
+    //todos created before the history was recorded have no events
+    if rows.is_empty() {
+        sqlx::query("SELECT id FROM todos WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&connection)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))?;
+    }
+
+    let items: Vec<TodoEvent> = rows.into_iter().map(TodoEvent::from).collect();
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
+}
+
+/// Returns the changes of all todo items, oldest change first.
+///
+/// Filter the events by todo, actor, operation and time, see [`EventParams`].
+/// Pass the `last_id` of a response as `after_id` to get the next page or to poll for new changes.
+///
+/// # Examples
+///
+/// Get the first events:
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/events
+/// ```
+///
+/// Get the todos `alice` deleted since the start of 2026, dates are epoch seconds:
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/events?actor=alice&operation=delete&since=1767225600
+/// ```
+///
+/// Get the events after event `120`:
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/events?after_id=120
+/// ```
+pub async fn list_events(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Query(params): Query<EventParams>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_events() with {:?}", params);
+
+    let count = params.count.unwrap_or(config.default_page_size).clamp(1, config.max_page_size);
+
+    let mut arguments = SqliteArguments::default();
+    let mut filter = String::from("WHERE 1 = 1");
+
+    if let Some(after_id) = params.after_id {
+        filter.push_str(" AND id > ?");
+        let _ = arguments.add(after_id);
+    }
+    if let Some(todo_id) = params.todo_id {
+        filter.push_str(" AND todo_id = ?");
+        let _ = arguments.add(todo_id);
+    }
+    if let Some(actor) = &params.actor {
+        filter.push_str(" AND actor = ?");
+        let _ = arguments.add(actor.clone());
+    }
+    if let Some(operation) = params.operation {
+        filter.push_str(" AND operation = ?");
+        let _ = arguments.add(operation);
+    }
+    if let Some(since) = params.since {
+        filter.push_str(" AND timestamp >= ?");
+        let _ = arguments.add(since);
+    }
+    if let Some(until) = params.until {
+        filter.push_str(" AND timestamp < ?");
+        let _ = arguments.add(until);
+    }
+
+    //one more event than requested tells if there is a next page
+    let _ = arguments.add(count + 1);
+
+    let mut rows: Vec<EventRow> = sqlx::query_as_with(&format!("
+        SELECT {}
+        FROM todo_events
+        {}
+        ORDER BY id
+        LIMIT ?
+    ", EVENT_COLUMNS, filter), arguments)
+    .fetch_all(&connection)
+    .await?;
+
+    let has_next = rows.len() as i64 > count;
+    rows.truncate(count as usize);
+
+    //without new events a client keeps polling after the same event
+    let last_id = rows.last().map(|row| row.id).or(params.after_id);
+
+    let next = has_next.then(|| {
+        let params = EventParams { count: Some(count), after_id: last_id, ..params.clone() };
+        format!("/events?{}", serde_urlencoded::to_string(&params).unwrap_or_default())
+    });
+
+    let items: Vec<TodoEvent> = rows.into_iter().map(TodoEvent::from).collect();
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items,
+        "last_id": last_id,
+        "next": next
+    })))
+}
+
+/// Set the fields of a todo item back to an earlier revision.
+///
+/// The revision is a version of the todo, see the `version` of the events in [`todo_history()`].
+/// All fields and tags are set to their values at that version, the todo stays out of the trash.
+/// The revert is recorded in the history like any other change and can be reverted itself.
+///
+/// With an `If-Match` header the todo is only reverted if its version matches, see [`get_todo()`](crate::handlers::get_todo).
+///
+/// Responds with `404 Not Found` if no todo with this ID exists or the revision was not recorded,
+/// with `412 Precondition Failed` if the todo was changed in the meantime
+/// and with `422 Unprocessable Entity` if the project or parent of the revision does not exist anymore.
+///
+/// # Examples
+///
+/// Set todo item with ID 10 back to version 3:
+///
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/10/revert?revision=3
+/// ```
+pub async fn revert_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, Query(params): Query<RevertParams>, headers: HeaderMap) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler revert_todo() with ID {} and {:?}", id, params);
+
+    let mut transaction = connection.begin().await?;
+
+    let current = snapshot(&mut transaction, id)
+        .await?
+        .filter(|current| current.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))?;
+
+    if let Some(versions) = if_match(&headers)
+        && !versions.contains(&current.version)
+    {
+        return Err(not_changed(&mut transaction, id).await);
+    }
+
+    let mut target = None;
+    if (1..=current.version).contains(&params.revision) {
+        target = revision(&mut transaction, id, params.revision).await?;
+    }
+    let mut target = target.ok_or_else(|| ApiError::NotFound(format!("Revision {} of todo with ID {} does not exist", params.revision, id)))?;
+
+    //a revert never moves the todo to the trash
+    target.deleted_at = None;
+
+    write_snapshot(&mut transaction, id, &current, &target).await?;
+    record(&mut transaction, id, Operation::Revert, &actor(&headers), Some(current)).await?;
+    transaction.commit().await?;
+
+    let response = item_response(&connection, id, None).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Undo the last request of a client session.
+///
+/// Clients send a session ID of their choice in the `X-Session-Id` header, every request with it can be undone.
+/// Each undo goes back one request, all changes of the request are undone together, eg a todo and its subtasks.
+/// Changed todos get their fields back, deleted todos are restored and created todos are moved to the trash.
+///
+/// Responds with the undone `operation` and the todos that are not in the trash after the undo in `items`.
+///
+/// Responds with `404 Not Found` if the session made no changes that can be undone,
+/// with `409 Conflict` if one of the todos was changed since or purged
+/// and with `422 Unprocessable Entity` if the `X-Session-Id` header is missing.
+///
+/// # Examples
+///
+/// ```bash
+/// curl -X DELETE http://localhost:3000/todos/10 -H 'X-Session-Id: 7f3c'
+/// curl -X POST http://localhost:3000/undo -H 'X-Session-Id: 7f3c'
+/// ```
+pub async fn undo(State(connection): State<SqlitePool>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler undo()");
+
+    let actor = actor(&headers);
+    let Some(session) = &actor.session else {
+        return Err(ApiError::Validation("Undo requires an X-Session-Id header".to_string()));
+    };
+
+    let mut transaction = connection.begin().await?;
+
+    //undos are not undone themselves, there is no redo
+    let request: String = sqlx::query_scalar("
+        SELECT request
+        FROM todo_events
+        WHERE session = ? AND undone = 0 AND operation != 'undo'
+        ORDER BY id DESC
+        LIMIT 1
+    ")
+    .bind(session)
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| ApiError::NotFound("No changes to undo".to_string()))?;
+
+    //latest change first, so a todo is undone step by step
+    let rows: Vec<EventRow> = sqlx::query_as(&format!("
+        SELECT {}
+        FROM todo_events
+        WHERE request = ?
+        ORDER BY id DESC
+    ", EVENT_COLUMNS))
+    .bind(&request)
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    //the latest change of every todo must still be its current version
+    let mut checked = HashSet::new();
+    for row in &rows {
+        if row.operation == Operation::Purge {
+            return Err(ApiError::Conflict(format!("Todo with ID {} was purged and cannot be restored", row.todo_id)));
+        }
+
+        if checked.insert(row.todo_id) {
+            let version = snapshot(&mut transaction, row.todo_id).await?.map(|current| current.version);
+            if version != Some(row.version) {
+                return Err(ApiError::Conflict(format!("Todo with ID {} was changed since, the change cannot be undone", row.todo_id)));
+            }
+        }
+    }
+
+    let operation = rows.last().map(|row| row.operation);
+    let mut ids = Vec::new();
+
+    for row in rows {
+        let id = row.todo_id;
+        let current = snapshot(&mut transaction, id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))?;
+
+        match row.before.and_then(|raw| serde_json::from_str::<TodoSnapshot>(&raw).ok()) {
+            Some(before) => write_snapshot(&mut transaction, id, &current, &before).await?,
+            None => trash_created(&mut transaction, id).await?,
+        }
+
+        record(&mut transaction, id, Operation::Undo, &actor, Some(current)).await?;
+
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    sqlx::query("UPDATE todo_events SET undone = 1 WHERE request = ?")
+        .bind(&request)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    //todos are loaded in the order of their first change
+    let mut items = Vec::new();
+    for &id in ids.iter().rev() {
+        match fetch_todo(&connection, id).await {
+            Ok(item) => items.push(item),
+            Err(ApiError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "operation": operation,
+        "items": items
+    })))
+}
+
+/// State of a todo at a revision, `None` if it was not recorded.
+///
+/// Todos created before the history was recorded have no state after their creation,
+/// the revision before their first recorded change is taken from that change.
+async fn revision(connection: &mut SqliteConnection, id: i64, revision: i64) -> Result<Option<TodoSnapshot>, sqlx::Error> {
+    //versions only increase, so the last change up to the revision holds its state
+    let mut raw: Option<String> = sqlx::query_scalar("
+        SELECT after
+        FROM todo_events
+        WHERE todo_id = ? AND version <= ? AND after IS NOT NULL
+        ORDER BY id DESC
+        LIMIT 1
+    ")
+    .bind(id)
+    .bind(revision)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    if raw.is_none() {
+        raw = sqlx::query_scalar("
+            SELECT before
+            FROM todo_events
+            WHERE todo_id = ? AND version = ? AND before IS NOT NULL
+            ORDER BY id
+            LIMIT 1
+        ")
+        .bind(id)
+        .bind(revision + 1)
+        .fetch_optional(&mut *connection)
+        .await?;
+    }
+
+    Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+}
+
+/// Overwrite the recorded fields of a todo with a snapshot, including tags and trash state.
+///
+/// The parent is only checked if it changes, a subtask can be restored before its parent.
+async fn write_snapshot(connection: &mut SqliteConnection, id: i64, current: &TodoSnapshot, target: &TodoSnapshot) -> Result<(), ApiError> {
+    if target.parent_id != current.parent_id
+        && let Some(parent_id) = target.parent_id
+    {
+        let mut errors = Vec::new();
+        check_parent(connection, &mut errors, Some(id), parent_id).await?;
+        into_result(errors)?;
+    }
+
+    sqlx::query("
+        UPDATE todos
+        SET title = ?, content = ?, done = ?, priority = ?, due_date = ?, finish_date = ?, project_id = ?, parent_id = ?, recurrence = ?, deleted_at = ?, updated_at = ?, version = version + 1
+        WHERE id = ?
+    ")
+    .bind(&target.title)
+    .bind(&target.content)
+    .bind(target.done)
+    .bind(target.priority)
+    .bind(target.due_date)
+    .bind(target.finish_date)
+    .bind(target.project_id)
+    .bind(target.parent_id)
+    .bind(&target.recurrence)
+    .bind(target.deleted_at)
+    .bind(now())
+    .bind(id)
+    .execute(&mut *connection)
+    .await
+    .map_err(unknown_project)?;
+
+    set_tags(connection, id, &target.tags).await
+}
+
+/// Move a todo to the trash to undo its creation.
+///
+/// Responds with `409 Conflict` if the todo got subtasks since, they would be left without parent.
+async fn trash_created(connection: &mut SqliteConnection, id: i64) -> Result<(), ApiError> {
+    if !descendants(connection, id).await?.is_empty() {
+        return Err(ApiError::Conflict(format!("Todo with ID {} has subtasks, the change cannot be undone", id)));
+    }
+
+    let now = now();
+    sqlx::query("UPDATE todos SET deleted_at = ?, updated_at = ?, version = version + 1 WHERE id = ? AND deleted_at IS NULL")
+        .bind(now)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(())
+}
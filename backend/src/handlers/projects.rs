@@ -0,0 +1,297 @@
+use axum::{Json, body::Bytes, extract::{Path, Query, State}, http::{HeaderMap, StatusCode, header}, response::IntoResponse};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteConnection, SqlitePool, SqliteQueryResult};
+
+use crate::data_structs::{DeleteMode, DeleteProjectParams, Operation, Project};
+use crate::error::ApiError;
+use crate::handlers::{now, placeholders};
+use crate::history::{actor, record_all, snapshots};
+use crate::validation::{FieldError, push};
+
+/// Returns all projects ordered by position.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/projects
+/// ```
+pub async fn list_projects(State(connection): State<SqlitePool>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_projects()");
+
+    let items: Vec<Project> = sqlx::query_as::<_, Project>("
+        SELECT id, name, color, position
+        FROM projects
+        ORDER BY position, id
+    ")
+    .fetch_all(&connection)
+    .await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
+}
+
+/// Returns a specific project by ID.
+/// 
+/// Responds with `404 Not Found` if no project with this ID exists.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/projects/2
+/// ```
+pub async fn get_project(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler get_project() with ID {}", id);
+
+    let item: Project = sqlx::query_as::<_, Project>("
+        SELECT id, name, color, position
+        FROM projects
+        WHERE id = ?
+    ")
+    .bind(id)
+    .fetch_optional(&connection)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Project with ID {} does not exist", id)))?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "item": item
+    })))
+}
+
+/// Create a new project.
+/// 
+/// The request body must contain the name of the project, colour and position are optional.
+/// The ID is generated by the database.
+/// 
+/// Responds with `201 Created`, a `Location` header and the created project.
+/// Responds with `422 Unprocessable Entity` if the name is empty or the colour is not a hex colour.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/projects -d '{"name":"Release 2.0","color":"#1e90ff","position":1}'
+/// ```
+pub async fn add_project(State(connection): State<SqlitePool>, body: Bytes) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler add_project() with {:?}", body);
+
+    //try to parse request body
+    let payload: Project = serde_json::from_slice(&body)?;
+    let payload = validate_project(payload)?;
+
+    let item: Project = sqlx::query_as::<_, Project>("
+        INSERT INTO projects (name, color, position)
+        VALUES (?, ?, ?)
+        RETURNING id, name, color, position
+    ")
+    .bind(payload.name)
+    .bind(payload.color)
+    .bind(payload.position)
+    .fetch_one(&connection)
+    .await?;
+
+    debug!("Handler result: Ok, inserted new project with ID {}", item.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/projects/{}", item.id))],
+        Json(json!({
+            "status": "ok",
+            "item": item
+        })),
+    ))
+}
+
+/// Update a specific project by ID.
+/// 
+/// # Attention
+/// 
+/// The request body must contain a complete project as json, a missing colour removes the colour.
+/// 
+/// Responds with `404 Not Found` if no project with this ID exists, see [`add_project()`] for the other errors.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X PUT http://localhost:3000/projects/2 -d '{"name":"Personal","color":"#ff8800","position":0}'
+/// ```
+pub async fn update_project(State(connection): State<SqlitePool>, Path(id): Path<i64>, body: Bytes) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler update_project() with {:?}", body);
+
+    //try to parse request body
+    let payload: Project = serde_json::from_slice(&body)?;
+    let payload = validate_project(payload)?;
+
+    let item: Project = sqlx::query_as::<_, Project>("
+        UPDATE projects
+        SET name = ?, color = ?, position = ?
+        WHERE id = ?
+        RETURNING id, name, color, position
+    ")
+    .bind(payload.name)
+    .bind(payload.color)
+    .bind(payload.position)
+    .bind(id)
+    .fetch_optional(&connection)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Project with ID {} does not exist", id)))?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "item": item
+    })))
+}
+
+/// Delete a specific project by ID.
+/// 
+/// By default the todo items of the project are kept and moved to the inbox.
+/// With `mode=cascade` they are moved to the trash together with their subtasks, see [`DeleteProjectParams`].
+/// Restored todos are in the inbox, see [`restore_todo()`](crate::handlers::trash::restore_todo).
+/// Either way the changed todos are recorded in their history.
+/// 
+/// Responds with `404 Not Found` if no project with this ID exists.
+/// 
+/// # Examples
+/// 
+/// Delete project 2 and move its todos to the inbox.
+/// 
+/// ```bash
+/// curl -X DELETE http://localhost:3000/projects/2
+/// ```
+/// 
+/// Delete project 2 and move all of its todos to the trash.
+/// 
+/// ```bash
+/// curl -X DELETE http://localhost:3000/projects/2?mode=cascade
+/// ```
+pub async fn delete_project(State(connection): State<SqlitePool>, Path(id): Path<i64>, Query(params): Query<DeleteProjectParams>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler delete_project() with ID {} and {:?}", id, params);
+
+    let actor = actor(&headers);
+    let mut transaction = connection.begin().await?;
+
+    match params.mode.unwrap_or(DeleteMode::Inbox) {
+        DeleteMode::Cascade => {
+            //subtasks in other projects are moved to the trash with their parent
+            let ids: Vec<i64> = sqlx::query_scalar("
+                WITH RECURSIVE trashed(id) AS (
+                    SELECT id FROM todos WHERE project_id = ? AND deleted_at IS NULL
+                    UNION
+                    SELECT t.id FROM todos t JOIN trashed p ON t.parent_id = p.id
+                    WHERE t.deleted_at IS NULL
+                )
+                SELECT id FROM trashed
+            ")
+            .bind(id)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+            let before = snapshots(&mut transaction, &ids).await?;
+
+            //the same time for all todos, so subtasks are restored together with their parent
+            //todos of the project are restored to the inbox
+            let query = format!("
+                UPDATE todos
+                SET deleted_at = ?, updated_at = ?, project_id = CASE WHEN project_id = ? THEN NULL ELSE project_id END, version = version + 1
+                WHERE id IN ({})
+            ", placeholders(ids.len()));
+
+            let now = now();
+            let mut query = sqlx::query(&query)
+                .bind(now)
+                .bind(now)
+                .bind(id);
+            for id in &ids {
+                query = query.bind(id);
+            }
+            query.execute(&mut *transaction).await?;
+
+            record_all(&mut transaction, before, Operation::Delete, &actor).await?;
+        }
+        DeleteMode::Inbox => {
+            let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM todos WHERE project_id = ?")
+                .bind(id)
+                .fetch_all(&mut *transaction)
+                .await?;
+
+            let before = snapshots(&mut transaction, &ids).await?;
+
+            sqlx::query("UPDATE todos SET project_id = NULL, updated_at = ?, version = version + 1 WHERE project_id = ?")
+                .bind(now())
+                .bind(id)
+                .execute(&mut *transaction)
+                .await?;
+
+            record_all(&mut transaction, before, Operation::Update, &actor).await?;
+        }
+    }
+
+    let result: SqliteQueryResult = sqlx::query("
+        DELETE FROM projects WHERE id = ?
+    ")
+    .bind(id)
+    .execute(&mut *transaction)
+    .await?;
+
+    //ID does not exits = no row got deleted, rollback
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound(format!("Project with ID {} does not exist", id)));
+    }
+
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// Check that a project can be stored, the name is trimmed.
+fn validate_project(mut project: Project) -> Result<Project, ApiError> {
+    project.name = project.name.trim().to_string();
+
+    if project.name.is_empty() {
+        return Err(ApiError::Validation("Project name must not be empty".to_string()));
+    }
+
+    if let Some(color) = &project.color {
+        let is_hex = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+        if !is_hex {
+            return Err(ApiError::Validation(format!("Invalid project color: {}, expected a hex colour like #1e90ff", color)));
+        }
+    }
+
+    Ok(project)
+}
+
+/// Check that the project of a todo exists, an unknown project is added to `errors`.
+pub(crate) async fn check_project(connection: &mut SqliteConnection, errors: &mut Vec<FieldError>, project_id: i64) -> Result<(), sqlx::Error> {
+    let exists = sqlx::query("SELECT id FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(&mut *connection)
+        .await?
+        .is_some();
+
+    if !exists {
+        push(errors, "project_id", format!("project with ID {} does not exist", project_id));
+    }
+
+    Ok(())
+}
+
+/// Map the foreign key of `todos.project_id` to a [`ApiError::InvalidFields`].
+/// 
+/// Catches projects deleted after [`check_project()`].
+pub(crate) fn unknown_project(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            ApiError::InvalidFields(vec![FieldError { field: "project_id".to_string(), message: "project does not exist".to_string() }])
+        }
+        e => ApiError::Database(e),
+    }
+}
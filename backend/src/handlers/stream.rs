@@ -0,0 +1,255 @@
+use std::{collections::{HashMap, VecDeque}, time::Duration};
+
+use axum::{extract::{Query, State}, http::HeaderMap, response::sse::{Event, KeepAlive, Sse}};
+use futures_util::stream::{self, Stream};
+use log::{debug, error};
+use sqlx::sqlite::SqlitePool;
+use tokio::sync::broadcast::{self, Receiver, Sender, error::RecvError};
+
+use crate::data_structs::{ChangeKind, Operation, StreamParams, TodoNotification};
+use crate::error::ApiError;
+use crate::handlers::fetch_including_trashed;
+use crate::history::{EVENT_COLUMNS, EventRow, TodoSnapshot};
+
+/// Time between two checks for new events.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum number of events loaded at once, a resumed stream catches up in batches.
+const BATCH_SIZE: i64 = 100;
+
+/// Number of notifications kept for slow streams, a stream that falls further behind catches up from the database.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Stream notifications about changed todo items as server-sent events.
+///
+/// Every change recorded in the history, see [`todo_history()`](crate::handlers::history::todo_history), is sent as an event
+/// named `created`, `updated`, `deleted` or `toggled` with a [`TodoNotification`] as JSON data.
+/// The notification carries the current state of the todo, also for todos in the trash.
+///
+/// The ID of every event is the ID of the recorded change. A client that reconnects with the `Last-Event-ID` header
+/// or the `after_id` query parameter gets all changes it missed, without either it only gets new changes.
+/// New changes are sent by [`watch_events()`] within a second, a comment is sent every 15 seconds to keep the connection open.
+///
+/// # Examples
+///
+/// ```bash
+/// curl -N http://localhost:3000/events/stream
+/// ```
+///
+/// Resume after the event with ID `120`:
+///
+/// ```bash
+/// curl -N http://localhost:3000/events/stream -H 'Last-Event-ID: 120'
+/// ```
+pub async fn stream_events(State(connection): State<SqlitePool>, State(sender): State<Sender<TodoNotification>>, Query(params): Query<StreamParams>, headers: HeaderMap) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    debug!("Called handler stream_events() with {:?}", params);
+
+    //subscribe first, changes recorded while the stream starts are not missed
+    let receiver = sender.subscribe();
+
+    //the header is sent by browsers when they reconnect, it wins over the parameter
+    let resume = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .or(params.after_id);
+
+    let last_id = match resume {
+        Some(id) => id,
+        None => latest_id(&connection).await?,
+    };
+
+    //changes after the start that the watcher skipped before the subscription are loaded from the database
+    let subscription = Subscription { connection, receiver, last_id, catching_up: true, pending: VecDeque::new() };
+    let events = stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next().await?;
+        Some((event, subscription))
+    });
+
+    debug!("Handler result: Ok");
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Channel for the notifications of [`watch_events()`], every stream subscribes to it.
+pub fn notification_channel() -> Sender<TodoNotification> {
+    broadcast::channel(CHANNEL_CAPACITY).0
+}
+
+/// Check the history for new changes and send their notifications to all streams.
+///
+/// A single task loads the changes for all streams, changes are skipped while no stream is open.
+/// Runs until the server stops, start it with `tokio::spawn`.
+pub async fn watch_events(connection: SqlitePool, sender: Sender<TodoNotification>) {
+    let mut last_id: Option<i64> = None;
+
+    loop {
+        //a failed check is repeated, the streams stay open
+        let full = match send_new(&connection, &sender, &mut last_id).await {
+            Ok(full) => full,
+            Err(e) => {
+                error!("Loading events for the streams failed: {}", e);
+                false
+            }
+        };
+
+        //a full batch is followed by the next one right away
+        if !full {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Send the notifications for the changes after `last_id`, returns `true` if the batch was full.
+async fn send_new(connection: &SqlitePool, sender: &Sender<TodoNotification>, last_id: &mut Option<i64>) -> Result<bool, ApiError> {
+    let after_id = match *last_id {
+        Some(after_id) if sender.receiver_count() > 0 => after_id,
+        _ => {
+            //read before checking again, a stream that subscribes after the check starts at or after this ID
+            let latest = latest_id(connection).await?;
+            match *last_id {
+                Some(after_id) if sender.receiver_count() > 0 => after_id,
+                //without streams the changes are skipped
+                _ => {
+                    *last_id = Some(latest);
+                    return Ok(false);
+                }
+            }
+        }
+    };
+
+    let batch = notifications(connection, after_id).await?;
+    let full = batch.len() as i64 == BATCH_SIZE;
+
+    for notification in batch {
+        *last_id = Some(notification.event_id);
+        //fails only if the last stream was closed in the meantime
+        let _ = sender.send(notification);
+    }
+
+    Ok(full)
+}
+
+/// State of a single stream.
+struct Subscription {
+    connection: SqlitePool,
+    receiver: Receiver<TodoNotification>,
+
+    /// ID of the last change sent
+    last_id: i64,
+
+    /// `true` while the missed changes are loaded from the database
+    catching_up: bool,
+
+    /// notifications loaded but not sent yet
+    pending: VecDeque<TodoNotification>,
+}
+
+impl Subscription {
+    /// Next event of the stream, `None` once the server stops.
+    async fn next(&mut self) -> Option<Result<Event, axum::Error>> {
+        loop {
+            if let Some(notification) = self.pending.pop_front() {
+                self.last_id = notification.event_id;
+                return Some(sse_event(&notification));
+            }
+
+            if self.catching_up {
+                //a failed load is repeated, the stream stays open
+                match notifications(&self.connection, self.last_id).await {
+                    Ok(batch) => {
+                        self.catching_up = batch.len() as i64 == BATCH_SIZE;
+                        self.pending.extend(batch);
+                    }
+                    Err(e) => {
+                        error!("Loading events for the stream failed: {}", e);
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                }
+                continue;
+            }
+
+            match self.receiver.recv().await {
+                //changes loaded while catching up are sent by the channel again
+                Ok(notification) if notification.event_id > self.last_id => self.pending.push_back(notification),
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => self.catching_up = true,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// ID of the last recorded change, `0` if nothing was recorded yet.
+async fn latest_id(connection: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM todo_events").fetch_one(connection).await
+}
+
+/// Server-sent event for a notification.
+fn sse_event(notification: &TodoNotification) -> Result<Event, axum::Error> {
+    Event::default()
+        .id(notification.event_id.to_string())
+        .event(notification.kind.name())
+        .json_data(notification)
+}
+
+/// Notifications for the recorded changes after `after_id`, at most [`BATCH_SIZE`].
+async fn notifications(connection: &SqlitePool, after_id: i64) -> Result<Vec<TodoNotification>, ApiError> {
+    let rows: Vec<EventRow> = sqlx::query_as(&format!("
+        SELECT {}
+        FROM todo_events
+        WHERE id > ?
+        ORDER BY id
+        LIMIT ?
+    ", EVENT_COLUMNS))
+    .bind(after_id)
+    .bind(BATCH_SIZE)
+    .fetch_all(connection)
+    .await?;
+
+    let mut ids: Vec<i64> = rows.iter().map(|row| row.todo_id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let items: HashMap<i64, _> = fetch_including_trashed(connection, &ids)
+        .await?
+        .into_iter()
+        .map(|item| (item.id, item))
+        .collect();
+
+    let notifications = rows
+        .into_iter()
+        .map(|row| {
+            let before = parse(row.before.as_deref());
+            let after = parse(row.after.as_deref());
+
+            TodoNotification {
+                event_id: row.id,
+                kind: change_kind(row.operation, before.as_ref(), after.as_ref()),
+                operation: row.operation,
+                todo_id: row.todo_id,
+                actor: row.actor,
+                timestamp: row.timestamp,
+                item: items.get(&row.todo_id).cloned(),
+            }
+        })
+        .collect();
+
+    Ok(notifications)
+}
+
+/// Snapshot stored as JSON text in the history.
+fn parse(raw: Option<&str>) -> Option<TodoSnapshot> {
+    raw.and_then(|raw| serde_json::from_str(raw).ok())
+}
+
+/// Kind of a change as clients see it, a todo in the trash does not exist for them.
+fn change_kind(operation: Operation, before: Option<&TodoSnapshot>, after: Option<&TodoSnapshot>) -> ChangeKind {
+    let visible = |snapshot: Option<&TodoSnapshot>| snapshot.is_some_and(|snapshot| snapshot.deleted_at.is_none());
+
+    match (visible(before), visible(after)) {
+        (false, true) => ChangeKind::Created,
+        (_, false) => ChangeKind::Deleted,
+        _ if operation == Operation::Toggle => ChangeKind::Toggled,
+        _ => ChangeKind::Updated,
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use axum::{Json, extract::{Query, State}};
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::sqlite::SqlitePool;
+
+use crate::data_structs::{SyncParams, TodoItem, Tombstone};
+use crate::error::ApiError;
+use crate::handlers::{TODO_COLUMNS, fetch_including_trashed};
+use crate::handlers::tags::load_tags;
+
+/// Returns the todo items that changed since the last sync.
+///
+/// Every change of a todo is recorded with an increasing ID, see [`list_events()`](crate::handlers::history::list_events).
+/// The response contains a `token`, the ID of the last change. Pass it as `since` to the next sync to get only the changes after it.
+///
+/// - `items` = todos created or changed since the token, in their current state
+/// - `tombstones` = todos moved to the trash or purged since the token, with the time of their deletion
+///
+/// A todo is returned once with its current state, even if it changed several times. It can be returned again by the next sync
+/// if it changed while the sync was running, applying it again does no harm.
+/// Without `since` all todos that are not in the trash are returned, use this for the first sync of a client.
+///
+/// Responds with `422 Unprocessable Entity` if the token is newer than the last change, eg from another database.
+/// The client has to sync again without token.
+///
+/// # Examples
+///
+/// First sync:
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/sync
+/// ```
+///
+/// Changes after the sync that returned token `120`:
+///
+/// ```bash
+/// curl -X GET http://localhost:3000/sync?since=120
+/// ```
+pub async fn sync_todos(State(connection): State<SqlitePool>, Query(params): Query<SyncParams>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler sync_todos() with {:?}", params);
+
+    //changes after the token are left for the next sync
+    let token: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM todo_events")
+        .fetch_one(&connection)
+        .await?;
+
+    let mut items = Vec::new();
+    let mut tombstones = Vec::new();
+
+    match params.since {
+        None => {
+            items = sqlx::query_as::<_, TodoItem>(&format!("
+                SELECT {}
+                FROM todos
+                WHERE deleted_at IS NULL
+                ORDER BY id
+            ", TODO_COLUMNS))
+            .fetch_all(&connection)
+            .await?;
+
+            load_tags(&connection, &mut items).await?;
+        }
+        Some(since) => {
+            if since > token {
+                return Err(ApiError::Validation(format!("Sync token {} is unknown, sync again without token", since)));
+            }
+
+            //time of the last change marks purged todos
+            let changed: Vec<(i64, DateTime<Utc>)> = sqlx::query_as("
+                SELECT todo_id, MAX(timestamp)
+                FROM todo_events
+                WHERE id > ? AND id <= ?
+                GROUP BY todo_id
+                ORDER BY todo_id
+            ")
+            .bind(since)
+            .bind(token)
+            .fetch_all(&connection)
+            .await?;
+
+            let ids: Vec<i64> = changed.iter().map(|(id, _)| *id).collect();
+            let mut current: HashMap<i64, TodoItem> = fetch_including_trashed(&connection, &ids)
+                .await?
+                .into_iter()
+                .map(|item| (item.id, item))
+                .collect();
+
+            for (id, changed_at) in changed {
+                match current.remove(&id) {
+                    Some(item) if item.deleted_at.is_none() => items.push(item),
+                    Some(item) => tombstones.push(Tombstone { id, deleted_at: item.deleted_at.unwrap_or(changed_at) }),
+                    None => tombstones.push(Tombstone { id, deleted_at: changed_at }),
+                }
+            }
+        }
+    }
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items,
+        "tombstones": tombstones,
+        "token": token
+    })))
+}
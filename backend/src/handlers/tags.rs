@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use axum::{Json, body::Bytes, extract::{Path, State}, http::{HeaderMap, StatusCode, header}, response::IntoResponse};
+use log::debug;
+use serde_json::{json, Value};
+use sqlx::{Arguments, Sqlite, SqliteConnection};
+use sqlx::sqlite::{SqliteArguments, SqlitePool, SqliteQueryResult};
+
+use crate::data_structs::{Operation, Tag, TodoItem};
+use crate::error::ApiError;
+use crate::handlers::{now, placeholders};
+use crate::history::{actor, record_all, snapshots};
+
+/// Returns all tags ordered by name.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/tags
+/// ```
+pub async fn list_tags(State(connection): State<SqlitePool>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_tags()");
+
+    let items: Vec<Tag> = sqlx::query_as::<_, Tag>("
+        SELECT id, name
+        FROM tags
+        ORDER BY name
+    ")
+    .fetch_all(&connection)
+    .await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "items": items
+    })))
+}
+
+/// Returns a specific tag by ID.
+/// 
+/// Responds with `404 Not Found` if no tag with this ID exists.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/tags/3
+/// ```
+pub async fn get_tag(State(connection): State<SqlitePool>, Path(id): Path<i64>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler get_tag() with ID {}", id);
+
+    let item: Tag = sqlx::query_as::<_, Tag>("
+        SELECT id, name
+        FROM tags
+        WHERE id = ?
+    ")
+    .bind(id)
+    .fetch_optional(&connection)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Tag with ID {} does not exist", id)))?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "item": item
+    })))
+}
+
+/// Create a new tag.
+/// 
+/// The request body must contain the name of the tag, the ID is generated by the database.
+/// 
+/// Responds with `201 Created`, a `Location` header and the created tag.
+/// Responds with `422 Unprocessable Entity` if the name is empty or contains a comma
+/// and with `409 Conflict` if a tag with this name already exists.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/tags -d '{"name":"work"}'
+/// ```
+pub async fn add_tag(State(connection): State<SqlitePool>, body: Bytes) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler add_tag() with {:?}", body);
+
+    //try to parse request body
+    let payload: Tag = serde_json::from_slice(&body)?;
+    let name = validate_tag_name(&payload.name)?;
+
+    let item: Tag = sqlx::query_as::<_, Tag>("
+        INSERT INTO tags (name)
+        VALUES (?)
+        RETURNING id, name
+    ")
+    .bind(&name)
+    .fetch_one(&connection)
+    .await
+    .map_err(|e| duplicate_name(e, &name))?;
+
+    debug!("Handler result: Ok, inserted new tag with ID {}", item.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/tags/{}", item.id))],
+        Json(json!({
+            "status": "ok",
+            "item": item
+        })),
+    ))
+}
+
+/// Rename a specific tag by ID.
+/// 
+/// All todo items keep the tag, they show the new name. Their version is increased and the change is recorded in their history.
+/// 
+/// Responds with `404 Not Found` if no tag with this ID exists, see [`add_tag()`] for the other errors.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X PUT http://localhost:3000/tags/3 -d '{"name":"office"}'
+/// ```
+pub async fn update_tag(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap, body: Bytes) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler update_tag() with {:?}", body);
+
+    //try to parse request body
+    let payload: Tag = serde_json::from_slice(&body)?;
+    let name = validate_tag_name(&payload.name)?;
+
+    let mut transaction = connection.begin().await?;
+
+    let ids = tagged_todos(&mut transaction, id).await?;
+    let before = snapshots(&mut transaction, &ids).await?;
+
+    let item: Tag = sqlx::query_as::<_, Tag>("
+        UPDATE tags
+        SET name = ?
+        WHERE id = ?
+        RETURNING id, name
+    ")
+    .bind(&name)
+    .bind(id)
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(|e| duplicate_name(e, &name))?
+    .ok_or_else(|| ApiError::NotFound(format!("Tag with ID {} does not exist", id)))?;
+
+    touch_todos(&mut transaction, &ids).await?;
+    record_all(&mut transaction, before, Operation::Update, &actor(&headers)).await?;
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({
+        "status": "ok",
+        "item": item
+    })))
+}
+
+/// Delete a specific tag by ID.
+/// 
+/// The tag is removed from all todo items, the todo items are kept. Their version is increased and the change is recorded in their history.
+/// 
+/// Responds with `404 Not Found` if no tag with this ID exists.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X DELETE http://localhost:3000/tags/3
+/// ```
+pub async fn delete_tag(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler delete_tag() with ID {}", id);
+
+    let mut transaction = connection.begin().await?;
+
+    let ids = tagged_todos(&mut transaction, id).await?;
+    let before = snapshots(&mut transaction, &ids).await?;
+
+    //links to todos are deleted by the foreign key
+    let result: SqliteQueryResult = sqlx::query("
+        DELETE FROM tags WHERE id = ?
+    ")
+    .bind(id)
+    .execute(&mut *transaction)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound(format!("Tag with ID {} does not exist", id)));
+    }
+
+    touch_todos(&mut transaction, &ids).await?;
+    record_all(&mut transaction, before, Operation::Update, &actor(&headers)).await?;
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// IDs of the todos with a tag, including todos in the trash.
+async fn tagged_todos(connection: &mut SqliteConnection, tag_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT todo_id FROM todo_tags WHERE tag_id = ?")
+        .bind(tag_id)
+        .fetch_all(&mut *connection)
+        .await
+}
+
+/// Increase the version of todos whose tags changed, so clients and `updated_since` see the change.
+async fn touch_todos(connection: &mut SqliteConnection, ids: &[i64]) -> Result<(), sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let query = format!("UPDATE todos SET updated_at = ?, version = version + 1 WHERE id IN ({})", placeholders(ids.len()));
+    let mut query = sqlx::query(&query).bind(now());
+    for id in ids {
+        query = query.bind(id);
+    }
+    query.execute(&mut *connection).await?;
+
+    Ok(())
+}
+
+/// Check that a tag name can be stored and return it trimmed.
+fn validate_tag_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(ApiError::Validation("Tag name must not be empty".to_string()));
+    }
+
+    //comma separates tags in the filters of list_todos
+    if name.contains(',') {
+        return Err(ApiError::Validation("Tag name must not contain a comma".to_string()));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Map the unique constraint of the tag name to a [`ApiError::Conflict`].
+fn duplicate_name(e: sqlx::Error, name: &str) -> ApiError {
+    match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            ApiError::Conflict(format!("Tag with name {} already exists", name))
+        }
+        e => ApiError::Database(e),
+    }
+}
+
+/// Split a comma separated list of tag names.
+/// 
+/// Names are trimmed, empty names are dropped.
+pub(crate) fn split_tags(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Replace all tags of a todo item.
+/// 
+/// Tags that do not exist yet are created. Names are trimmed, empty names and
+/// names containing a comma are rejected. Call this inside the transaction
+/// that writes the todo item.
+pub(crate) async fn set_tags(connection: &mut SqliteConnection, todo_id: i64, tags: &[String]) -> Result<(), ApiError> {
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = ?")
+        .bind(todo_id)
+        .execute(&mut *connection)
+        .await?;
+
+    //names are unique case insensitive
+    let mut seen = HashSet::new();
+
+    for tag in tags {
+        let name = validate_tag_name(tag)?;
+        if !seen.insert(name.to_lowercase()) {
+            continue;
+        }
+
+        sqlx::query("INSERT INTO tags (name) VALUES (?) ON CONFLICT (name) DO NOTHING")
+            .bind(&name)
+            .execute(&mut *connection)
+            .await?;
+
+        sqlx::query("
+            INSERT INTO todo_tags (todo_id, tag_id)
+            SELECT ?, id FROM tags WHERE name = ?
+        ")
+        .bind(todo_id)
+        .bind(&name)
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Fill the tags of the given todo items from the database.
+pub(crate) async fn load_tags<'c, E>(connection: E, items: &mut [TodoItem]) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Sqlite>,
+{
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let mut arguments = SqliteArguments::default();
+    for item in items.iter() {
+        let _ = arguments.add(item.id);
+    }
+
+    let query = format!("
+        SELECT tt.todo_id, t.name
+        FROM todo_tags tt
+        JOIN tags t ON t.id = tt.tag_id
+        WHERE tt.todo_id IN ({})
+        ORDER BY t.name
+    ", super::placeholders(items.len()));
+
+    let rows: Vec<(i64, String)> = sqlx::query_as_with(&query, arguments)
+        .fetch_all(connection)
+        .await?;
+
+    for item in items.iter_mut() {
+        item.tags = rows.iter()
+            .filter(|(todo_id, _)| *todo_id == item.id)
+            .map(|(_, name)| name.clone())
+            .collect();
+    }
+
+    Ok(())
+}
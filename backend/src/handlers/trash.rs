@@ -0,0 +1,260 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{Json, extract::{Path, Query, State}, http::HeaderMap, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info};
+use serde_json::{json, Value};
+use sqlx::SqliteConnection;
+use sqlx::sqlite::SqlitePool;
+
+use crate::config::Config;
+use crate::data_structs::{Operation, Order, QueryParams, SortBy};
+use crate::error::ApiError;
+use crate::handlers::{item_response, list_todos, now, placeholders};
+use crate::history::{Actor, actor, record_all, snapshots};
+
+/// Returns the todo items in the trash, most recently deleted first.
+/// 
+/// Takes the same query parameters as [`list_todos()`], including filters, sorting and pagination.
+/// Todos stay in the trash until they are restored with [`restore_todo()`] or purged,
+/// either with [`purge_trash()`] or automatically after `trash_retention_days`.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/trash
+/// ```
+/// 
+/// Search the trash for todos containing `invoice`:
+/// 
+/// ```bash
+/// curl -X GET http://localhost:3000/trash?search=invoice
+/// ```
+pub async fn list_trash(State(connection): State<SqlitePool>, State(config): State<Arc<Config>>, Query(params): Query<QueryParams>) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler list_trash() with {:?}", params);
+
+    //without a sorting the last deleted todo is first
+    let sort_by = match (&params.sort, params.sort_by, params.search.is_some()) {
+        (None, None, false) => Some(SortBy::DeletedAt),
+        (_, sort_by, _) => sort_by,
+    };
+    let order = params.order.or(sort_by.map(|_| Order::Desc));
+
+    let params = QueryParams { trashed: Some(true), sort_by, order, ..params };
+
+    list_todos(State(connection), State(config), Query(params)).await
+}
+
+/// Restore a specific todo item from the trash.
+/// 
+/// Subtasks that were deleted together with the todo are restored too.
+/// 
+/// Responds with the restored todo and its version as `ETag`.
+/// Responds with `404 Not Found` if no todo with this ID is in the trash
+/// and with `409 Conflict` if the parent of the todo is still in the trash.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X POST http://localhost:3000/todos/42/restore
+/// ```
+pub async fn restore_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap) -> Result<impl IntoResponse, ApiError> {
+    debug!("Called handler restore_todo() with ID {}", id);
+
+    let mut transaction = connection.begin().await?;
+
+    let (deleted_at, parent_id): (Option<DateTime<Utc>>, Option<i64>) = sqlx::query_as("SELECT deleted_at, parent_id FROM todos WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Todo with ID {} does not exist", id)))?;
+
+    let Some(deleted_at) = deleted_at else {
+        return Err(ApiError::NotFound(format!("Todo with ID {} is not in the trash", id)));
+    };
+
+    //a restored subtask would be hidden below its deleted parent
+    if let Some(parent_id) = parent_id {
+        let parent_deleted: Option<DateTime<Utc>> = sqlx::query_scalar("SELECT deleted_at FROM todos WHERE id = ?")
+            .bind(parent_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        if parent_deleted.is_some() {
+            return Err(ApiError::Conflict(format!("Parent todo with ID {} is in the trash, restore it first", parent_id)));
+        }
+    }
+
+    //subtasks deleted at the same time were deleted together with the todo
+    let ids: Vec<i64> = sqlx::query_scalar("
+        WITH RECURSIVE restored(id) AS (
+            SELECT ?
+            UNION
+            SELECT t.id FROM todos t JOIN restored r ON t.parent_id = r.id
+            WHERE t.deleted_at = ?
+        )
+        SELECT id FROM restored
+    ")
+    .bind(id)
+    .bind(deleted_at)
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    let before = snapshots(&mut transaction, &ids).await?;
+
+    let query = format!("
+        UPDATE todos
+        SET deleted_at = NULL, updated_at = ?, version = version + 1
+        WHERE id IN ({})
+    ", placeholders(ids.len()));
+
+    let mut query = sqlx::query(&query).bind(now());
+    for id in &ids {
+        query = query.bind(id);
+    }
+    query.execute(&mut *transaction).await?;
+
+    record_all(&mut transaction, before, Operation::Restore, &actor(&headers)).await?;
+    transaction.commit().await?;
+
+    let response = item_response(&connection, id, None).await?;
+
+    debug!("Handler result: Ok");
+    Ok(response)
+}
+
+/// Delete all todo items in the trash for good.
+/// 
+/// Responds with the number of purged todos in `deleted`.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X DELETE http://localhost:3000/trash
+/// ```
+pub async fn purge_trash(State(connection): State<SqlitePool>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler purge_trash()");
+
+    let mut transaction = connection.begin().await?;
+
+    let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM todos WHERE deleted_at IS NOT NULL")
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    let purged = purge(&mut transaction, &ids, &actor(&headers)).await?;
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok, purged {} todos", purged);
+    Ok(Json(json!({
+        "status": "ok",
+        "deleted": purged
+    })))
+}
+
+/// Delete a specific todo item in the trash for good, together with its subtasks.
+/// 
+/// Responds with `404 Not Found` if no todo with this ID is in the trash.
+/// 
+/// # Examples
+/// 
+/// ```bash
+/// curl -X DELETE http://localhost:3000/trash/42
+/// ```
+pub async fn purge_todo(State(connection): State<SqlitePool>, Path(id): Path<i64>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    debug!("Called handler purge_todo() with ID {}", id);
+
+    let mut transaction = connection.begin().await?;
+
+    let trashed = sqlx::query("SELECT id FROM todos WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .is_some();
+
+    if !trashed {
+        return Err(ApiError::NotFound(format!("Todo with ID {} is not in the trash", id)));
+    }
+
+    //subtasks are purged together with the todo
+    purge(&mut transaction, &[id], &actor(&headers)).await?;
+    transaction.commit().await?;
+
+    debug!("Handler result: Ok");
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// Delete the todos that are in the trash for longer than `retention_days`.
+/// 
+/// Returns the number of purged todos.
+pub async fn purge_expired(connection: &SqlitePool, retention_days: u32) -> Result<u64, sqlx::Error> {
+    let expired = now() - chrono::Duration::days(retention_days.into());
+
+    let mut transaction = connection.begin().await?;
+
+    //subtasks expire together with their parent, see purge()
+    let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM todos WHERE deleted_at < ?")
+        .bind(expired)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    let purged = purge(&mut transaction, &ids, &Actor::system()).await?;
+    transaction.commit().await?;
+
+    Ok(purged)
+}
+
+/// Delete the todos with the given IDs for good and record it in their history.
+/// 
+/// Subtasks are deleted with their parent by the foreign key, also subtasks that are not in the trash
+/// because their deletion was undone. They are recorded too, so clients learn that they are gone.
+/// Returns the number of purged todos, including the subtasks.
+async fn purge(connection: &mut SqliteConnection, ids: &[i64], actor: &Actor) -> Result<u64, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let query = format!("
+        WITH RECURSIVE purged(id) AS (
+            SELECT id FROM todos WHERE id IN ({})
+            UNION
+            SELECT t.id FROM todos t JOIN purged p ON t.parent_id = p.id
+        )
+        SELECT id FROM purged
+    ", placeholders(ids.len()));
+    let mut query = sqlx::query_scalar(&query);
+    for id in ids {
+        query = query.bind(id);
+    }
+    let purged: Vec<i64> = query.fetch_all(&mut *connection).await?;
+
+    let before = snapshots(connection, &purged).await?;
+
+    let query = format!("DELETE FROM todos WHERE id IN ({})", placeholders(ids.len()));
+    let mut query = sqlx::query(&query);
+    for id in ids {
+        query = query.bind(id);
+    }
+    query.execute(&mut *connection).await?;
+
+    record_all(connection, before, Operation::Purge, actor).await?;
+    Ok(purged.len() as u64)
+}
+
+/// Purge expired todos from the trash once per hour, see [`purge_expired()`].
+/// 
+/// Runs until the server stops, start it with `tokio::spawn`.
+pub async fn purge_periodically(connection: SqlitePool, retention_days: u32) {
+    //the first tick completes immediately, so the trash is purged on startup
+    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+
+    loop {
+        interval.tick().await;
+
+        match purge_expired(&connection, retention_days).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} todos from the trash", purged),
+            Err(e) => error!("Purging the trash failed: {}", e),
+        }
+    }
+}
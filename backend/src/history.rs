@@ -0,0 +1,240 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::data_structs::{Operation, TodoEvent};
+use crate::handlers::now;
+
+/// Header that names who makes a change, eg the user of a client.
+pub const ACTOR_HEADER: &str = "x-actor";
+
+/// Header that identifies a client session, the changes of a session can be undone.
+pub const SESSION_HEADER: &str = "x-session-id";
+
+/// Actor of requests without `X-Actor` header.
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Actor of changes the server makes by itself, eg purging the trash.
+pub const SYSTEM: &str = "system";
+
+/// Longer actor names and session IDs are cut off.
+const MAX_ACTOR_LENGTH: usize = 100;
+
+/// Columns of the `todo_events` table that make up an [`EventRow`].
+pub(crate) const EVENT_COLUMNS: &str = "id, todo_id, version, timestamp, actor, operation, before, after";
+
+/// Who makes the changes of a request.
+#[derive(Debug, Clone)]
+pub struct Actor {
+    /// name from the `X-Actor` header
+    pub name: String,
+
+    /// client session from the `X-Session-Id` header, see [`undo()`](crate::handlers::history::undo)
+    pub session: Option<String>,
+
+    /// unique for every request, all changes of a request are undone together
+    pub request: String,
+}
+
+impl Actor {
+    /// Actor of changes the server makes by itself, see [`SYSTEM`].
+    pub fn system() -> Self {
+        Actor { name: SYSTEM.to_string(), session: None, request: Uuid::new_v4().to_string() }
+    }
+}
+
+/// Who makes the changes of a request, taken from the `X-Actor` and `X-Session-Id` headers.
+///
+/// The name is [`ANONYMOUS`] if the header is missing or empty.
+pub fn actor(headers: &HeaderMap) -> Actor {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_ACTOR_LENGTH).collect::<String>())
+    };
+
+    Actor {
+        name: header(ACTOR_HEADER).unwrap_or_else(|| ANONYMOUS.to_string()),
+        session: header(SESSION_HEADER),
+        request: Uuid::new_v4().to_string(),
+    }
+}
+
+/// Fields of a todo item that are recorded in its history.
+///
+/// ID, creation date and update time are left out, they never change or change with every event.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, sqlx::FromRow)]
+pub struct TodoSnapshot {
+    pub title: String,
+    pub content: String,
+    pub done: bool,
+    pub priority: i16,
+
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub due_date: Option<DateTime<Utc>>,
+
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub finish_date: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+
+    pub project_id: Option<i64>,
+    pub parent_id: Option<i64>,
+    pub recurrence: Option<String>,
+
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// stored in the `version` column of the event, not in the snapshot
+    #[serde(skip)]
+    pub version: i64,
+}
+
+/// Database row of a [`TodoEvent`], the snapshots are stored as JSON text.
+#[derive(sqlx::FromRow)]
+pub(crate) struct EventRow {
+    pub id: i64,
+    pub todo_id: i64,
+    pub version: i64,
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub operation: Operation,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl From<EventRow> for TodoEvent {
+    fn from(row: EventRow) -> Self {
+        let before = row.before.and_then(|raw| serde_json::from_str(&raw).ok());
+        let after = row.after.and_then(|raw| serde_json::from_str(&raw).ok());
+        let (before, after) = diff(before, after);
+
+        TodoEvent {
+            id: row.id,
+            todo_id: row.todo_id,
+            version: row.version,
+            timestamp: row.timestamp,
+            actor: row.actor,
+            operation: row.operation,
+            before,
+            after,
+        }
+    }
+}
+
+/// Reduce two snapshots to the fields that differ.
+///
+/// A snapshot without counterpart, eg of a created todo, keeps all fields.
+fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    match (before, after) {
+        (Some(Value::Object(mut before)), Some(Value::Object(mut after))) => {
+            let changed: Vec<String> = after
+                .iter()
+                .filter(|(field, value)| before.get(*field) != Some(value))
+                .map(|(field, _)| field.clone())
+                .collect();
+
+            before.retain(|field, _| changed.contains(field));
+            after.retain(|field, _| changed.contains(field));
+
+            (Some(Value::Object(before)), Some(Value::Object(after)))
+        }
+        other => other,
+    }
+}
+
+/// Current state of the recorded fields of a todo, `None` if it does not exist.
+///
+/// Todos in the trash are included.
+pub async fn snapshot(connection: &mut SqliteConnection, id: i64) -> Result<Option<TodoSnapshot>, sqlx::Error> {
+    let snapshot: Option<TodoSnapshot> = sqlx::query_as("
+        SELECT title, content, done, priority, due_date, finish_date, project_id, parent_id, recurrence, deleted_at, version
+        FROM todos
+        WHERE id = ?
+    ")
+    .bind(id)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    let Some(mut snapshot) = snapshot else {
+        return Ok(None);
+    };
+
+    snapshot.tags = sqlx::query_scalar("
+        SELECT t.name
+        FROM todo_tags tt
+        JOIN tags t ON t.id = tt.tag_id
+        WHERE tt.todo_id = ?
+        ORDER BY t.name
+    ")
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    Ok(Some(snapshot))
+}
+
+/// Snapshots of several todos before they are changed together, see [`record_all()`].
+pub async fn snapshots(connection: &mut SqliteConnection, ids: &[i64]) -> Result<Vec<(i64, Option<TodoSnapshot>)>, sqlx::Error> {
+    let mut snapshots = Vec::with_capacity(ids.len());
+    for &id in ids {
+        snapshots.push((id, snapshot(connection, id).await?));
+    }
+
+    Ok(snapshots)
+}
+
+/// Record a change of a todo in its history.
+///
+/// `before` is the snapshot taken before the change, the state after the change is read from the database.
+/// Nothing is recorded if the todo did not change.
+pub async fn record(connection: &mut SqliteConnection, id: i64, operation: Operation, actor: &Actor, before: Option<TodoSnapshot>) -> Result<(), sqlx::Error> {
+    let after = snapshot(connection, id).await?;
+
+    //a request that changes no field still increments the version
+    let unchanged = match (&before, &after) {
+        (Some(before), Some(after)) => TodoSnapshot { version: before.version, ..after.clone() } == *before,
+        (before, after) => before.is_none() && after.is_none(),
+    };
+    if unchanged {
+        return Ok(());
+    }
+
+    //a purged todo keeps its last version
+    let version = after.as_ref().or(before.as_ref()).map_or(0, |snapshot| snapshot.version);
+
+    sqlx::query("
+        INSERT INTO todo_events (todo_id, version, timestamp, actor, session, request, operation, before, after)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ")
+    .bind(id)
+    .bind(version)
+    .bind(now())
+    .bind(&actor.name)
+    .bind(&actor.session)
+    .bind(&actor.request)
+    .bind(operation)
+    .bind(before.map(|snapshot| json!(snapshot).to_string()))
+    .bind(after.map(|snapshot| json!(snapshot).to_string()))
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a change of several todos, `before` are the [`snapshots()`] taken before the change.
+pub async fn record_all(connection: &mut SqliteConnection, before: Vec<(i64, Option<TodoSnapshot>)>, operation: Operation, actor: &Actor) -> Result<(), sqlx::Error> {
+    for (id, snapshot) in before {
+        record(connection, id, operation, actor, snapshot).await?;
+    }
+
+    Ok(())
+}
@@ -5,4 +5,31 @@
 pub mod handlers;
 
 /// Data structures the API depends on
-pub mod data_structs;
\ No newline at end of file
+pub mod data_structs;
+
+/// Error type returned by the API endpoints
+pub mod error;
+
+/// Database setup and schema migrations
+pub mod database;
+
+/// Configuration of the server
+pub mod config;
+
+/// State shared by all handlers
+pub mod state;
+
+/// Recurrence rules of repeating todo items
+pub mod recurrence;
+
+/// Full-text search over todo items
+pub mod search;
+
+/// Cursors for keyset pagination
+pub mod cursor;
+
+/// Validation of todo items before they are saved
+pub mod validation;
+
+/// Change history of todo items
+pub mod history;
@@ -1,54 +1,104 @@
 // Jakob Frenzel
 // 11/12/25
 
-use axum::{Router, routing::get};
+use std::{str::FromStr, sync::Arc};
+
+use axum::{Router, routing::{delete, get, post}};
 use log::{info, LevelFilter};
 use simple_logger::SimpleLogger;
-use sqlx::{Executor, sqlite::{SqliteConnectOptions, SqlitePool}};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 
-use tick_backend::handlers::{add_todo, autocomplete_todos, delete_todo, get_todo, list_todos, update_todo};
+use tick_backend::config::Config;
+use tick_backend::database::run_migrations;
+#[allow(deprecated)]
+use tick_backend::handlers::{add_todo, autocomplete_todos, complete_todo, delete_todo, get_todo, list_children, list_occurrences, list_todos, patch_todo, reopen_todo, toggle_todo, update_todo};
+use tick_backend::handlers::tags::{add_tag, delete_tag, get_tag, list_tags, update_tag};
+use tick_backend::handlers::bulk::{bulk_todos, delete_todos};
+use tick_backend::handlers::history::{list_events, revert_todo, todo_history, undo};
+use tick_backend::handlers::stream::{notification_channel, stream_events, watch_events};
+use tick_backend::handlers::sync::sync_todos;
+use tick_backend::handlers::trash::{list_trash, purge_periodically, purge_todo, purge_trash, restore_todo};
+use tick_backend::handlers::projects::{add_project, delete_project, get_project, list_projects, update_project};
+use tick_backend::state::AppState;
 
 //https://docs.rs/axum/latest/axum/#example
+#[allow(deprecated)]
 #[tokio::main]
 async fn main() {
+    //load config from command line, environment and config file
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(2);
+    });
+
     //init log
+    //dependencies log at most info
     SimpleLogger::new()
-        .with_level(LevelFilter::Info)
-        .with_module_level("tick_backend", LevelFilter::Debug)
+        .with_level(config.log_level.min(LevelFilter::Info))
+        .with_module_level("tick_backend", config.log_level)
         .init()
         .unwrap();
 
     info!("Application version: {}", env!("CARGO_PKG_VERSION"));
+    info!("Configuration: {:?}", config);
 
     // connect to database
     // create new file if it does not exist
     // https://medium.com/@mikecode/rust-sqlx-sqlite-8d66dbe5e497
-    let option = SqliteConnectOptions::new().filename("data/todos.db").create_if_missing(true);
-    let connection = SqlitePool::connect_with(option).await.unwrap();
-
-    //create table in database if it does not exist
-    //based on struct TodoItem
-    connection.execute("
-            CREATE TABLE IF NOT EXISTS todos (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                content TEXT,
-                done INTEGER NOT NULL DEFAULT 0,
-                priority INTEGER,
-                creation_date INTEGER NOT NULL,
-                due_date INTEGER,
-                finish_date INTEGER
-            );
-        ").await.unwrap();
-
-    let app = Router::new()
-        .route("/todos", get(list_todos).post(add_todo))
-        .route("/todos/{id}", get(get_todo).delete(delete_todo).put(update_todo))
+    let option = SqliteConnectOptions::from_str(&config.database_url).unwrap().create_if_missing(true);
+    let connection = SqlitePoolOptions::new()
+        .max_connections(config.pool_size)
+        .connect_with(option)
+        .await
+        .unwrap();
+
+    //create or update the tables in the database
+    //see the migrations directory
+    run_migrations(&connection).await.unwrap();
+
+    //purge expired todos from the trash in the background
+    if config.trash_retention_days > 0 {
+        tokio::spawn(purge_periodically(connection.clone(), config.trash_retention_days));
+    }
+
+    let cors = config.cors_layer();
+    let bind_address = config.bind_address;
+    //a single task loads the changes for all event streams
+    let notifications = notification_channel();
+    tokio::spawn(watch_events(connection.clone(), notifications.clone()));
+
+    let state = AppState { connection, config: Arc::new(config), notifications };
+
+    let mut app = Router::new()
+        .route("/todos", get(list_todos).post(add_todo).delete(delete_todos))
+        .route("/todos/bulk", post(bulk_todos))
+        .route("/todos/{id}", get(get_todo).delete(delete_todo).put(update_todo).patch(patch_todo))
+        .route("/todos/{id}/toggle", post(toggle_todo))
+        .route("/todos/{id}/complete", post(complete_todo))
+        .route("/todos/{id}/reopen", post(reopen_todo))
+        .route("/todos/{id}/restore", post(restore_todo))
+        .route("/todos/{id}/children", get(list_children))
+        .route("/todos/{id}/occurrences", get(list_occurrences))
+        .route("/todos/{id}/history", get(todo_history))
+        .route("/todos/{id}/revert", post(revert_todo))
         .route("/todos/autocomplete", get(autocomplete_todos))
-        .with_state(connection);
+        .route("/events", get(list_events))
+        .route("/events/stream", get(stream_events))
+        .route("/sync", get(sync_todos))
+        .route("/undo", post(undo))
+        .route("/trash", get(list_trash).delete(purge_trash))
+        .route("/trash/{id}", delete(purge_todo))
+        .route("/tags", get(list_tags).post(add_tag))
+        .route("/tags/{id}", get(get_tag).put(update_tag).delete(delete_tag))
+        .route("/projects", get(list_projects).post(add_project))
+        .route("/projects/{id}", get(get_project).put(update_project).delete(delete_project))
+        .with_state(state);
+
+    if let Some(cors) = cors {
+        app = app.layer(cors);
+    }
 
-    // listen globally on port 3000
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    info!("Server started successfully at 0.0.0.0:3000");
+    let listener = tokio::net::TcpListener::bind(bind_address).await.unwrap();
+    info!("Server started successfully at {}", bind_address);
     axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+}
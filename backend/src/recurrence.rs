@@ -0,0 +1,87 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rrule::{RRule, RRuleSet, Tz, Unvalidated};
+
+use crate::error::ApiError;
+
+/// Clean up the recurrence rule of a todo item and check that it is valid.
+///
+/// The rule is a RFC 5545 `RRULE` value like `FREQ=WEEKLY;BYDAY=MO`, the `RRULE:` prefix is optional and removed.
+/// An empty rule means the todo does not repeat.
+///
+/// Responds with [`ApiError::Validation`] if the rule cannot be parsed.
+pub fn normalize_rule(rule: Option<String>) -> Result<Option<String>, ApiError> {
+    let Some(rule) = rule else {
+        return Ok(None);
+    };
+
+    let rule = rule.trim();
+    let rule = match rule.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => rule[6..].trim(),
+        _ => rule,
+    };
+
+    if rule.is_empty() {
+        return Ok(None);
+    }
+
+    build(rule, Utc::now())?;
+    Ok(Some(rule.to_string()))
+}
+
+/// Next `count` occurrences of the rule after `start`.
+///
+/// `start` is the first occurrence of the rule, usually the due date of the todo, and is not returned.
+pub fn next_occurrences(rule: &str, start: DateTime<Utc>, count: usize) -> Result<Vec<DateTime<Utc>>, ApiError> {
+    let set = build(rule, start)?.limit();
+
+    Ok(set
+        .into_iter()
+        .filter(|date| *date > start)
+        .take(count)
+        .map(|date| date.with_timezone(&Utc))
+        .collect())
+}
+
+/// Due date and rule of the occurrence that follows the one due at `start`.
+///
+/// A `COUNT` in the rule is reduced by the occurrences that are used up, so the series still ends in time.
+/// Returns `None` if the series has ended.
+pub fn next_occurrence(rule: &str, start: DateTime<Utc>) -> Result<Option<(DateTime<Utc>, String)>, ApiError> {
+    let Some(next) = next_occurrences(rule, start, 1)?.pop() else {
+        return Ok(None);
+    };
+
+    let parsed = parse(rule)?;
+    let Some(count) = parsed.get_count() else {
+        return Ok(Some((next, rule.to_string())));
+    };
+
+    //start is only used up if it is an occurrence itself
+    let set = build(rule, start)?.limit();
+    let used = if set.into_iter().next().is_some_and(|date| date == start) { 1 } else { 0 };
+
+    let rule = rule
+        .split(';')
+        .map(|part| match part.split_once('=') {
+            Some((key, _)) if key.eq_ignore_ascii_case("COUNT") => format!("COUNT={}", count - used),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(";");
+
+    Ok(Some((next, rule)))
+}
+
+/// Parse a rule without validating it against a start date.
+fn parse(rule: &str) -> Result<RRule<Unvalidated>, ApiError> {
+    RRule::from_str(rule).map_err(|e| ApiError::Validation(format!("Invalid recurrence rule {}: {}", rule, e)))
+}
+
+/// Parse and validate a rule that starts at `start`.
+fn build(rule: &str, start: DateTime<Utc>) -> Result<RRuleSet, ApiError> {
+    parse(rule)?
+        .build(start.with_timezone(&Tz::UTC))
+        .map_err(|e| ApiError::Validation(format!("Invalid recurrence rule {}: {}", rule, e)))
+}
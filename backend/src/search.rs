@@ -0,0 +1,91 @@
+/// Expression that ranks full-text matches, lower is better.
+///
+/// Matches in the title weigh more than matches in the content.
+pub const RANK: &str = "bm25(todos_fts, 10.0, 1.0)";
+
+/// Expression that returns the best matching part of a todo with the matches marked as `<mark>...</mark>`.
+pub const SNIPPET: &str = "snippet(todos_fts, -1, '<mark>', '</mark>', '…', 12)";
+
+/// A single word or phrase of a search.
+struct Term {
+    text: String,
+    prefix: bool,
+}
+
+/// Translate a search entered by a user into a FTS5 query for the `todos_fts` table.
+///
+/// - words must all match, `deploy server` finds todos containing both words
+/// - a word ending with `*` matches as prefix, `dep*` finds `deploy` and `depends`
+/// - text in double quotes matches as phrase, `"weekly review"`
+///
+/// Other characters have no special meaning, so user input cannot break the query.
+/// Returns `None` if the search contains no words.
+pub fn match_query(search: &str) -> Option<String> {
+    render(parse(search))
+}
+
+/// Like [`match_query()`], but the last word always matches as prefix, as it might not be typed completely.
+pub fn autocomplete_query(search: &str) -> Option<String> {
+    let mut terms = parse(search);
+
+    if let Some(last) = terms.last_mut() {
+        last.prefix = true;
+    }
+
+    render(terms)
+}
+
+/// Split a search into words and phrases.
+fn parse(search: &str) -> Vec<Term> {
+    let mut terms = Vec::new();
+    let mut chars = search.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        //phrase until the closing quote or the end of the search
+        let mut text = String::new();
+        if c == '"' {
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                text.push(c);
+            }
+        } else {
+            text.push(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"') {
+                text.push(c);
+            }
+        }
+
+        let prefix = text.ends_with('*') || chars.next_if_eq(&'*').is_some();
+        let text = text.trim_end_matches('*').trim().to_string();
+
+        if !text.is_empty() {
+            terms.push(Term { text, prefix });
+        }
+    }
+
+    terms
+}
+
+/// Quote every term, so FTS5 does not interpret operators or special characters in it.
+fn render(terms: Vec<Term>) -> Option<String> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    let query = terms
+        .iter()
+        .map(|term| {
+            let quoted = format!("\"{}\"", term.text.replace('"', "\"\""));
+            if term.prefix { quoted + "*" } else { quoted }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    Some(query)
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use sqlx::sqlite::SqlitePool;
+use tokio::sync::broadcast::Sender;
+
+use crate::config::Config;
+use crate::data_structs::TodoNotification;
+
+/// Shared state of the server that is passed to the handlers.
+///
+/// Handlers extract only the part they need, eg `State<SqlitePool>` or `State<Arc<Config>>`.
+#[derive(Clone)]
+pub struct AppState {
+    /// connection pool of the database
+    pub connection: SqlitePool,
+
+    /// configuration the server was started with
+    pub config: Arc<Config>,
+
+    /// notifications for the event streams, see [`watch_events()`](crate::handlers::stream::watch_events)
+    pub notifications: Sender<TodoNotification>,
+}
+
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.connection.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Sender<TodoNotification> {
+    fn from_ref(state: &AppState) -> Self {
+        state.notifications.clone()
+    }
+}
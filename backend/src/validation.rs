@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::data_structs::{TodoItem, TodoPatch};
+use crate::error::ApiError;
+use crate::recurrence::normalize_rule;
+
+/// Invalid field of a request body and why it is invalid.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FieldError {
+    /// name of the field, eg `title`
+    pub field: String,
+
+    /// reason the value is invalid, eg `must not be empty`
+    pub message: String,
+}
+
+/// Check all fields of a todo item before it is saved.
+///
+/// `creation_date` is the creation date of the stored todo, or the current time for a new todo.
+/// The due date is only checked if it differs from `due_date`, the due date of the stored todo,
+/// an occurrence of a recurring todo that was completed late can be due before it was created.
+/// The limits are taken from the [`Config`].
+///
+/// Returns every invalid field, fields that need the database are checked by the caller, see [`into_result()`].
+pub fn validate_todo(item: &TodoItem, creation_date: DateTime<Utc>, due_date: Option<DateTime<Utc>>, config: &Config) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    check_title(&mut errors, &item.title, config);
+    check_content(&mut errors, &item.content, config);
+    check_priority(&mut errors, item.priority, config);
+    if item.due_date != due_date {
+        check_due_date(&mut errors, item.due_date, creation_date);
+    }
+    check_tags(&mut errors, &item.tags);
+
+    errors
+}
+
+/// Check the fields present in a partial update, see [`validate_todo()`].
+pub fn validate_patch(patch: &TodoPatch, creation_date: DateTime<Utc>, due_date: Option<DateTime<Utc>>, config: &Config) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    if let Some(title) = &patch.title {
+        check_title(&mut errors, title, config);
+    }
+    if let Some(content) = &patch.content {
+        check_content(&mut errors, content, config);
+    }
+    if let Some(priority) = patch.priority {
+        check_priority(&mut errors, priority, config);
+    }
+    if let Some(new_due_date) = patch.due_date
+        && new_due_date != due_date
+    {
+        check_due_date(&mut errors, new_due_date, creation_date);
+    }
+    if let Some(tags) = &patch.tags {
+        check_tags(&mut errors, tags);
+    }
+
+    errors
+}
+
+/// Recurrence rule must be valid, returns the normalized rule, see [`normalize_rule()`].
+pub fn check_recurrence(errors: &mut Vec<FieldError>, rule: Option<String>) -> Option<String> {
+    normalize_rule(rule).unwrap_or_else(|e| {
+        push(errors, "recurrence", e.to_string());
+        None
+    })
+}
+
+/// Title must contain text and not exceed the configured length.
+fn check_title(errors: &mut Vec<FieldError>, title: &str, config: &Config) {
+    if title.trim().is_empty() {
+        push(errors, "title", "must not be empty".to_string());
+    } else if title.chars().count() > config.max_title_length {
+        push(errors, "title", format!("must not be longer than {} characters", config.max_title_length));
+    }
+}
+
+/// Content may be empty but not exceed the configured length.
+fn check_content(errors: &mut Vec<FieldError>, content: &str, config: &Config) {
+    if content.chars().count() > config.max_content_length {
+        push(errors, "content", format!("must not be longer than {} characters", config.max_content_length));
+    }
+}
+
+/// Priority must be between `0` and the configured maximum.
+fn check_priority(errors: &mut Vec<FieldError>, priority: i16, config: &Config) {
+    if !(0..=config.max_priority).contains(&priority) {
+        push(errors, "priority", format!("must be between 0 and {}", config.max_priority));
+    }
+}
+
+/// A todo cannot be due before it was created.
+fn check_due_date(errors: &mut Vec<FieldError>, due_date: Option<DateTime<Utc>>, creation_date: DateTime<Utc>) {
+    if due_date.is_some_and(|due_date| due_date < creation_date) {
+        push(errors, "due_date", "must not be earlier than the creation date".to_string());
+    }
+}
+
+/// Tag names must contain text and no comma, as lists of tags are comma separated.
+fn check_tags(errors: &mut Vec<FieldError>, tags: &[String]) {
+    if tags.iter().any(|tag| tag.trim().is_empty()) {
+        push(errors, "tags", "must not contain empty names".to_string());
+    }
+    if tags.iter().any(|tag| tag.contains(',')) {
+        push(errors, "tags", "must not contain a comma".to_string());
+    }
+}
+
+/// Add an invalid field to the list.
+pub fn push(errors: &mut Vec<FieldError>, field: &str, message: String) {
+    errors.push(FieldError { field: field.to_string(), message });
+}
+
+/// Responds with [`ApiError::InvalidFields`] listing every invalid field, if there is any.
+pub fn into_result(errors: Vec<FieldError>) -> Result<(), ApiError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::InvalidFields(errors))
+    }
+}
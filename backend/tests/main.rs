@@ -3,36 +3,34 @@
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
-    use axum::{body::Bytes, extract::{Path, Query, State}};
-    use tick_backend::{data_structs::QueryParams, handlers::{add_todo, autocomplete_todos, delete_todo, get_todo, list_todos, update_todo}};
+    use std::{collections::HashMap, sync::Arc};
+
+    use axum::{body::Bytes, extract::{Path, Query, State}, http::{HeaderMap, StatusCode, header}};
+    #[allow(deprecated)]
+    use tick_backend::{config::{Config, ConfigArgs, ConfigError}, data_structs::QueryParams, database::{MIGRATOR, run_migrations}, handlers::{add_todo, autocomplete_todos, complete_todo, delete_todo, get_todo, list_children, list_occurrences, list_todos, patch_todo, reopen_todo, toggle_todo, update_todo}};
+    use tick_backend::handlers::tags::{add_tag, delete_tag, get_tag, list_tags, update_tag};
+    use tick_backend::handlers::bulk::{bulk_todos, delete_todos};
+    use tick_backend::handlers::history::{list_events, revert_todo, todo_history, undo};
+    use tick_backend::handlers::stream::{notification_channel, stream_events, watch_events};
+    use tick_backend::handlers::sync::sync_todos;
+    use tick_backend::handlers::trash::{list_trash, purge_expired, purge_todo, purge_trash, restore_todo};
+    use tick_backend::handlers::projects::{add_project, delete_project, get_project, list_projects, update_project};
+    use tick_backend::data_structs::{DeleteMode, DeleteProjectParams, DoneFilter, EventParams, Operation, RevertParams, Nulls, OccurrenceParams, Order, SortBy, StreamParams, SyncParams, UpdateParams};
     use axum::{response::IntoResponse};
     use serde_json::{Value};
-    use sqlx::{Executor, sqlite::{SqliteConnectOptions, SqlitePool}};
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
     use uuid::Uuid;
 
     async fn setup_test_db() -> SqlitePool {
         //create unique db in memory
         let db_name = format!("file:{}?mode=memory&cache=shared", Uuid::new_v4());
         let options = SqliteConnectOptions::new().filename(&db_name).create_if_missing(true);
-        let connection = SqlitePool::connect_with(options).await.unwrap();
-        connection
+        SqlitePool::connect_with(options).await.unwrap()
     }
 
     async fn populate_test_db(connection: SqlitePool){
-        connection.execute("
-                CREATE TABLE IF NOT EXISTS todos (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    title TEXT NOT NULL,
-                    content TEXT,
-                    done INTEGER NOT NULL DEFAULT 0,
-                    priority INTEGER,
-                    creation_date INTEGER NOT NULL,
-                    due_date INTEGER,
-                    finish_date INTEGER
-                );
-            ").await.unwrap();
+        //create tables with the same migrations the server runs
+        run_migrations(&connection).await.unwrap();
 
         //add two dummy entries
         //minimum entry
@@ -73,8 +71,8 @@ mod tests {
         assert_eq!(json["done"], false);
         assert_eq!(json["priority"], 0);
         assert_eq!(json["creation_date"], 1);
-        assert_eq!(json["due_date"], 0);
-        assert_eq!(json["finish_date"], 0);
+        assert_eq!(json["due_date"], Value::Null);
+        assert_eq!(json["finish_date"], Value::Null);
     }
 
     fn assert_db_item2(json: Value){
@@ -87,6 +85,24 @@ mod tests {
         assert_eq!(json["finish_date"], 3);
     }
 
+    #[tokio::test]
+    async fn test_run_migrations() {
+        let connection = setup_test_db().await;
+
+        //running the migrations again on an up to date database is a no-op
+        run_migrations(&connection).await.unwrap();
+        run_migrations(&connection).await.unwrap();
+
+        //every migration is recorded exactly once
+        let versions: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(&connection)
+            .await
+            .unwrap();
+        let expected: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+
+        assert_eq!(versions, expected);
+    }
+
     #[tokio::test]
     async fn test_list_todos() {
         let connection = setup_test_db().await;
@@ -94,6 +110,7 @@ mod tests {
         //call handler function on empty database
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: None,
                 offset: None,
@@ -101,10 +118,13 @@ mod tests {
                 order: None,
                 done: None,
                 search: None,
+                ..Default::default()
             }),
         )
         .await
         .into_response();
+        //missing table is a database error
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
         //convert the response to a json object so we can check specific keys
         let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let mut json: Value = serde_json::from_slice(&body).unwrap();
@@ -119,6 +139,7 @@ mod tests {
 
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: None,
                 offset: None,
@@ -126,6 +147,7 @@ mod tests {
                 order: None,
                 done: None,
                 search: None,
+                ..Default::default()
             }),
         )
         .await
@@ -139,6 +161,7 @@ mod tests {
         //get 100 todos
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: Some(100),
                 offset: None,
@@ -146,6 +169,7 @@ mod tests {
                 order: None,
                 done: None,
                 search: None,
+                ..Default::default()
             }),
         )
         .await
@@ -159,6 +183,7 @@ mod tests {
         //get 2 oldest todos
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: Some(2),
                 offset: Some(98),
@@ -166,6 +191,7 @@ mod tests {
                 order: None,
                 done: None,
                 search: None,
+                ..Default::default()
             }),
         )
         .await
@@ -183,13 +209,15 @@ mod tests {
         //get all true times
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: Some(100),
                 offset: None,
                 sort_by: None,
                 order: None,
-                done: Some(true),
+                done: Some(DoneFilter::Done),
                 search: None,
+                ..Default::default()
             }),
         )
         .await
@@ -204,13 +232,15 @@ mod tests {
         //get item based on title
         let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: Some(100),
                 offset: None,
                 sort_by: None,
                 order: None,
                 done: None,
-                search: Some("est2".to_string()),
+                search: Some("test2".to_string()),
+                ..Default::default()
             }),
         )
         .await
@@ -230,14 +260,16 @@ mod tests {
 
         //unknown id
         let mut response = get_todo(State(connection.clone()), Path(100)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let mut json: Value = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(json["status"], "error");
-        assert_eq!(json["message"], "no rows returned by a query that expected to return at least one row");
+        assert_eq!(json["message"], "Todo with ID 100 does not exist");
 
         //get item id 1
         response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
@@ -260,7 +292,8 @@ mod tests {
 
         //malformed json
         let malformed_json = r#"{}"#;
-        let mut response = update_todo(State(connection.clone()), Path(0), Bytes::from(malformed_json)).await.into_response();
+        let mut response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(0), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(malformed_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let mut json: Value = serde_json::from_slice(&body).unwrap();
 
@@ -269,7 +302,8 @@ mod tests {
 
         //non existend id
         let item_json = r#"{"content":"updated content","creation_date":0,"done":true,"finish_date":10,"due_date":20,"id":0,"priority":100,"title":"updated title"}"#;
-        response = update_todo(State(connection.clone()), Path(100), Bytes::from(item_json)).await.into_response();
+        response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(100), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
@@ -278,11 +312,16 @@ mod tests {
 
         //update item 1
         let item_json = r#"{"content":"updated content","creation_date":0,"done":true,"finish_date":10,"due_date":20,"id":0,"priority":100,"title":"updated title"}"#;
-        response = update_todo(State(connection.clone()), Path(1), Bytes::from(item_json)).await.into_response();
+        response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(json["status"], "ok");
+        //returned item is the stored row, id and creation date are unchanged
+        assert_eq!(json["item"]["id"], 1);
+        assert_eq!(json["item"]["title"], "updated title");
+        assert_eq!(json["item"]["creation_date"], 1);
 
         //read back item 1
         response = get_todo(State(connection.clone()), Path(1)).await.into_response();
@@ -297,7 +336,82 @@ mod tests {
         assert_eq!(json["item"]["priority"], 100);
         assert_eq!(json["item"]["creation_date"], 1);
         assert_eq!(json["item"]["due_date"], 20);
-        assert_eq!(json["item"]["finish_date"], 10);
+        //the finish date is set by the server
+        assert!(json["item"]["finish_date"].as_i64().unwrap() > 10);
+    }
+
+    #[tokio::test]
+    async fn test_patch_todo() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+
+        //wrong type for a field
+        let malformed_json = r#"{"done":"yes"}"#;
+        let mut response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(malformed_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], "error");
+
+        //non existend id
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(100), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"patched"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["message"], "Todo with ID 100 does not exist");
+
+        //empty patch returns the unchanged item
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], "ok");
+        assert_db_item2(json["item"].clone());
+
+        //only update title and done of item 2
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"patched title","done":false}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["item"]["title"], "patched title");
+        assert_eq!(json["item"]["done"], false);
+
+        //read back item 2, all other fields are unchanged
+        response = get_todo(State(connection.clone()), Path(2)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["item"]["title"], "patched title");
+        assert_eq!(json["item"]["content"], "Hello, World!");
+        assert_eq!(json["item"]["done"], false);
+        assert_eq!(json["item"]["priority"], 1);
+        assert_eq!(json["item"]["creation_date"], 2);
+        assert_eq!(json["item"]["due_date"], 4);
+        //reopening removes the finish date
+        assert_eq!(json["item"]["finish_date"], Value::Null);
+
+        //a finish date from the client is ignored, marking the todo as done sets the time of the server
+        let before = chrono::Utc::now().timestamp();
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true,"finish_date":10}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        let finish_date = json["item"]["finish_date"].as_i64().unwrap();
+        assert!(finish_date >= before);
+
+        //a todo that stays done keeps its finish date
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true,"finish_date":null}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["finish_date"], finish_date);
     }
 
     #[tokio::test]
@@ -306,7 +420,8 @@ mod tests {
         populate_test_db(connection.clone()).await;
 
         //unknown ID
-        let mut response = delete_todo(State(connection.clone()), Path(100)).await.into_response();
+        let mut response = delete_todo(State(connection.clone()), Path(100), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let mut json: Value = serde_json::from_slice(&body).unwrap();
 
@@ -314,14 +429,15 @@ mod tests {
         assert_eq!(json["message"], "Todo with ID 100 does not exist");
 
         //delete ID 1
-        response = delete_todo(State(connection.clone()), Path(1)).await.into_response();
+        response = delete_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(json["status"], "ok");
 
         //read back todos
-        response = list_todos(State(connection.clone()), Query(QueryParams {count: None, offset: None, sort_by: None, order: None, done: None, search: None}),).await.into_response();
+        response = list_todos(State(connection.clone()), State(Arc::new(Config::default())), Query(QueryParams {count: None, offset: None, sort_by: None, order: None, done: None, search: None, ..Default::default()}),).await.into_response();
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
@@ -333,6 +449,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(deprecated)]
     async fn test_autocomplete_todos() {
         let connection = setup_test_db().await;
         populate_test_db(connection.clone()).await;
@@ -358,9 +475,9 @@ mod tests {
         assert_eq!(json["items"].as_array().unwrap().len(), 0);
 
         //search for item 1
-        //title contains a "1"
+        //title is "Test1"
         params.clear();
-        params.insert("q".to_string(), "1".to_string());
+        params.insert("q".to_string(), "Test1".to_string());
         response = autocomplete_todos(State(connection.clone()), Query(params.clone())).await.into_response();
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
@@ -369,6 +486,14 @@ mod tests {
         assert_eq!(json["items"].as_array().unwrap().len(), 1);
         assert_db_item1(json["items"][0].clone());
 
+        //words match from their start, not in the middle
+        params.insert("q".to_string(), "est1".to_string());
+        response = autocomplete_todos(State(connection.clone()), Query(params.clone())).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 0);
+
         //search for item 2
         //description contains a "Hello"
         params.clear();
@@ -382,7 +507,7 @@ mod tests {
         assert_db_item2(json["items"][0].clone());
 
         //search for both
-        //both titles contain "Test"
+        //both titles start with "Test", the last word matches as prefix
         //also checking if not case senitive
         params.clear();
         params.insert("q".to_string(), "TEsT".to_string());
@@ -399,39 +524,35 @@ mod tests {
     #[tokio::test]
     async fn test_add_todo() {
         let connection = setup_test_db().await;
-        //create table
-        connection.execute("
-                CREATE TABLE IF NOT EXISTS todos (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    title TEXT NOT NULL,
-                    content TEXT,
-                    done INTEGER NOT NULL DEFAULT 0,
-                    priority INTEGER,
-                    creation_date INTEGER NOT NULL,
-                    due_date INTEGER,
-                    finish_date INTEGER
-                );
-            ").await.unwrap();
+        //create tables
+        run_migrations(&connection).await.unwrap();
 
         //malformed json
         let malformed_json = r#"{}"#;
-        let mut response = add_todo(State(connection.clone()), Bytes::from(malformed_json)).await.into_response();
+        let mut response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(malformed_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         let mut json: Value = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(json["status"], "error");
         assert_eq!(json["message"], "Invalid JSON: missing field `id` at line 1 column 2");
 
-        let item_json = r#"{"content":"new content","creation_date":1,"done":true,"finish_date":10,"due_date":20,"id":100,"priority":100,"title":"new title"}"#;
-        response = add_todo(State(connection.clone()), Bytes::from(item_json)).await.into_response();
+        let item_json = r#"{"content":"new content","creation_date":1,"done":true,"finish_date":10,"due_date":2000000000,"id":100,"priority":100,"title":"new title"}"#;
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], "/todos/1");
         body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
         json = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(json["status"], "ok");
+        //id is generated by the database and not taken from the request
+        assert_eq!(json["item"]["id"], 1);
+        assert_eq!(json["item"]["title"], "new title");
 
         //read created item
          let response = list_todos(
             State(connection.clone()),
+            State(Arc::new(Config::default())),
             Query(QueryParams {
                 count: None,
                 offset: None,
@@ -439,6 +560,7 @@ mod tests {
                 order: None,
                 done: None,
                 search: None,
+                ..Default::default()
             }),
         )
         .await
@@ -454,8 +576,1693 @@ mod tests {
         assert_eq!(json["items"][0]["id"], 1);
         assert_eq!(json["items"][0]["done"], true);
         assert_eq!(json["items"][0]["priority"], 100);
-        assert_eq!(json["items"][0]["creation_date"], 1);
-        assert_eq!(json["items"][0]["due_date"], 20);
-        assert_eq!(json["items"][0]["finish_date"], 10);
+        //creation date is set by the server
+        assert!(json["items"][0]["creation_date"].as_i64().unwrap() > 1);
+        assert_eq!(json["items"][0]["creation_date"], json["items"][0]["updated_at"]);
+        assert_eq!(json["items"][0]["due_date"], 2000000000);
+        //so is the finish date of a todo created as done
+        assert_eq!(json["items"][0]["finish_date"], json["items"][0]["creation_date"]);
+    }
+
+    #[tokio::test]
+    async fn test_config() {
+        //nothing set uses the defaults
+        let config = Config::from_args(ConfigArgs::default()).unwrap();
+        assert_eq!(config.bind_address.to_string(), "0.0.0.0:3000");
+        assert_eq!(config.database_url, "sqlite://data/todos.db");
+        assert_eq!(config.default_page_size, 25);
+        assert_eq!(config.max_page_size, 100);
+        assert_eq!(config.max_title_length, 200);
+        assert_eq!(config.max_content_length, 10_000);
+        assert_eq!(config.max_priority, i16::MAX);
+
+        //write config file
+        let path = std::env::temp_dir().join(format!("tick-{}.toml", Uuid::new_v4()));
+        std::fs::write(&path, r#"
+            bind_address = "127.0.0.1:4000"
+            pool_size = 2
+            max_page_size = 50
+            max_title_length = 80
+            cors_origins = ["http://localhost:1420"]
+        "#).unwrap();
+
+        //arguments take precedence over the file, the file over the defaults
+        let config = Config::from_args(ConfigArgs {
+            config: Some(path.clone()),
+            bind_address: Some("127.0.0.1:5000".parse().unwrap()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(config.bind_address.to_string(), "127.0.0.1:5000");
+        assert_eq!(config.pool_size, 2);
+        assert_eq!(config.max_page_size, 50);
+        assert_eq!(config.default_page_size, 25);
+        assert_eq!(config.max_title_length, 80);
+        assert_eq!(config.cors_origins, vec!["http://localhost:1420".to_string()]);
+        assert!(config.cors_layer().is_some());
+
+        //unknown keys in the file are rejected
+        std::fs::write(&path, "port = 4000").unwrap();
+        assert!(matches!(Config::from_args(ConfigArgs { config: Some(path.clone()), ..Default::default() }), Err(ConfigError::Toml(..))));
+        std::fs::remove_file(&path).unwrap();
+
+        //missing file
+        assert!(matches!(Config::from_args(ConfigArgs { config: Some(path), ..Default::default() }), Err(ConfigError::Io(..))));
+
+        //default page size larger than maximum
+        assert!(matches!(Config::from_args(ConfigArgs { default_page_size: Some(200), ..Default::default() }), Err(ConfigError::Invalid(_))));
+
+        //limits apply to list_todos
+        let connection = setup_test_db().await;
+        for _ in 0..10 {
+            populate_test_db(connection.clone()).await;
+        }
+
+        let config = Config::from_args(ConfigArgs { default_page_size: Some(3), max_page_size: Some(5), ..Default::default() }).unwrap();
+        let mut response = list_todos(State(connection.clone()), State(Arc::new(config.clone())), Query(QueryParams {count: None, offset: None, sort_by: None, order: None, done: None, search: None, ..Default::default()}),).await.into_response();
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 3);
+
+        response = list_todos(State(connection.clone()), State(Arc::new(config)), Query(QueryParams {count: Some(100), offset: None, sort_by: None, order: None, done: None, search: None, ..Default::default()}),).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+
+        //empty name
+        let mut response = add_tag(State(connection.clone()), Bytes::from(r#"{"name":"  "}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //create tag
+        response = add_tag(State(connection.clone()), Bytes::from(r#"{"name":"work"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], "/tags/1");
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["id"], 1);
+        assert_eq!(json["item"]["name"], "work");
+
+        //names are unique, not case sensitive
+        response = add_tag(State(connection.clone()), Bytes::from(r#"{"name":"Work"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        response = add_tag(State(connection.clone()), Bytes::from(r#"{"name":"home"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        //rename tag
+        response = update_tag(State(connection.clone()), Path(1), HeaderMap::new(), Bytes::from(r#"{"name":"office"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = update_tag(State(connection.clone()), Path(100), HeaderMap::new(), Bytes::from(r#"{"name":"office"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = get_tag(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["name"], "office");
+
+        //delete tag
+        response = delete_tag(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = delete_tag(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = list_tags(State(connection.clone())).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["name"], "home");
+    }
+
+    #[tokio::test]
+    async fn test_todo_tags() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        //unknown tags are created, duplicates are dropped
+        let item_json = r#"{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"first","tags":["work","urgent","Work"]}"#;
+        let mut response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["tags"], serde_json::json!(["urgent", "work"]));
+
+        //no tags
+        let item_json = r#"{"content":"","creation_date":2,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"second"}"#;
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let item_json = r#"{"content":"","creation_date":3,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"third","tags":["home"]}"#;
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        //single tag
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { tag: Some("WORK".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["title"], "first");
+        assert_eq!(json["items"][0]["tags"], serde_json::json!(["urgent", "work"]));
+
+        //any of the tags
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { any_tag: Some("home,urgent".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+        //all of the tags
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { all_tags: Some("home,urgent".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 0);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { all_tags: Some("urgent, work".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["title"], "first");
+
+        //patch replaces the tags, other patches keep them
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"tags":["home"]}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["tags"], serde_json::json!(["home"]));
+
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"renamed"}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["tags"], serde_json::json!(["home"]));
+
+        //invalid tag name rolls back the whole update
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"invalid","tags":["a,b"]}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //deleting a tag removes it from the todos
+        let tag_id: i64 = sqlx::query_scalar("SELECT id FROM tags WHERE name = 'home'").fetch_one(&connection).await.unwrap();
+        response = delete_tag(State(connection.clone()), Path(tag_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["title"], "renamed");
+        assert_eq!(json["item"]["tags"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_projects() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+
+        //invalid colour
+        let mut response = add_project(State(connection.clone()), Bytes::from(r##"{"name":"Release 2.0","color":"blue"}"##)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //create projects
+        response = add_project(State(connection.clone()), Bytes::from(r##"{"name":"Release 2.0","color":"#1e90ff","position":2}"##)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], "/projects/1");
+
+        response = add_project(State(connection.clone()), Bytes::from(r##"{"name":"Personal","position":1}"##)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        //ordered by position
+        response = list_projects(State(connection.clone())).await.into_response();
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert_eq!(json["items"][0]["name"], "Personal");
+        assert_eq!(json["items"][0]["color"], Value::Null);
+        assert_eq!(json["items"][1]["name"], "Release 2.0");
+        assert_eq!(json["items"][1]["color"], "#1e90ff");
+
+        //update project
+        response = update_project(State(connection.clone()), Path(2), Bytes::from(r##"{"name":"Private","color":"#ff8800","position":3}"##)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = update_project(State(connection.clone()), Path(100), Bytes::from(r##"{"name":"Private"}"##)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = get_project(State(connection.clone()), Path(2)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["name"], "Private");
+        assert_eq!(json["item"]["color"], "#ff8800");
+        assert_eq!(json["item"]["position"], 3);
+
+        //todos in project 1, project 2 and the inbox
+        let config = Arc::new(Config::default());
+        for (title, project) in [("first", "1"), ("second", "1"), ("third", "2"), ("fourth", "null")] {
+            let item_json = format!(r#"{{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"{}","project_id":{}}}"#, title, project);
+            response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        //unknown project
+        let item_json = r#"{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"lost","project_id":100}"#;
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { project: Some("1".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { project: Some("inbox".to_string()), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["title"], "fourth");
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { project: Some("release".to_string()), ..Default::default() })).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //move todo to the inbox with a patch
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"project_id":null}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["project_id"], Value::Null);
+
+        //delete project 2, todos are moved to the inbox
+        response = delete_project(State(connection.clone()), Path(2), Query(DeleteProjectParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = get_todo(State(connection.clone()), Path(3)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["project_id"], Value::Null);
+
+        //delete project 1 with its todos
+        response = delete_project(State(connection.clone()), Path(1), Query(DeleteProjectParams { mode: Some(DeleteMode::Cascade) }), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 3);
+
+        //the todos are in the trash and can be restored to the inbox
+        response = todo_history(State(connection.clone()), Path(1)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().last().unwrap()["operation"], "delete");
+
+        response = restore_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["title"], "first");
+        assert_eq!(json["item"]["project_id"], Value::Null);
+
+        response = delete_project(State(connection.clone()), Path(1), Query(DeleteProjectParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_subtasks() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        //todo 3 and 4 are subtasks of todo 1, todo 5 is a subtask of todo 3
+        for (title, parent) in [("child", 1), ("other child", 1), ("grandchild", 3)] {
+            let item_json = format!(r#"{{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"{}","parent_id":{}}}"#, title, parent);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        //unknown parent
+        let item_json = r#"{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"orphan","parent_id":100}"#;
+        let mut response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //direct children only
+        response = list_children(State(connection.clone()), Path(1)).await.into_response();
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert_eq!(json["items"][0]["title"], "child");
+        assert_eq!(json["items"][0]["children_total"], 1);
+
+        response = list_children(State(connection.clone()), Path(100)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //progress of the parent
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["children_total"], 2);
+        assert_eq!(json["item"]["children_done"], 0);
+
+        //top-level todos only
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { top_level: Some(true), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+        //nested tree
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams { tree: Some(true), sort_by: Some(SortBy::CreationDate), order: Some(Order::Asc), ..Default::default() })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert_eq!(json["items"][0]["title"], "Test1");
+        assert_eq!(json["items"][0]["children"][0]["children"][0]["title"], "grandchild");
+        assert!(json["items"][1].get("children").is_none());
+
+        //a todo cannot become a subtask of itself or its subtasks
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"parent_id":5}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"parent_id":1}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //moving a subtask to another parent is fine
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(4), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"parent_id":2}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //complete todo 1 together with its subtasks
+        let params = UpdateParams { complete_children: Some(true) };
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(params), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["children_done"], 1);
+        let finish_date = json["item"]["finish_date"].clone();
+
+        response = get_todo(State(connection.clone()), Path(5)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["done"], true);
+        assert_eq!(json["item"]["finish_date"], finish_date);
+
+        //subtasks are deleted with their parent
+        response = delete_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = get_todo(State(connection.clone()), Path(5)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_recurrence() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        //monday 01/01/35 09:00, one week apart
+        let due_date = 2051254800;
+        let week = 7 * 24 * 60 * 60;
+
+        //invalid rule
+        let item_json = r#"{"content":"","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"review","recurrence":"FREQ=SOMETIMES"}"#;
+        let mut response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //weekly review, three times
+        let item_json = format!(r#"{{"content":"notes","creation_date":1,"done":false,"finish_date":0,"due_date":{},"id":0,"priority":2,"title":"review","tags":["work"],"recurrence":"RRULE:FREQ=WEEKLY;COUNT=3"}}"#, due_date);
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["recurrence"], "FREQ=WEEKLY;COUNT=3");
+
+        //preview
+        response = list_occurrences(State(connection.clone()), State(config.clone()), Path(1), Query(OccurrenceParams { count: Some(5) })).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"], serde_json::json!([due_date + week, due_date + 2 * week]));
+
+        response = list_occurrences(State(connection.clone()), State(config.clone()), Path(100), Query(OccurrenceParams::default())).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //completing the todo creates the next occurrence
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["recurrence"], Value::Null);
+        //completing and moving the rule to the next occurrence is a single change
+        assert_eq!(json["item"]["version"], 2);
+        assert_eq!(json["next"]["id"], 2);
+        assert_eq!(json["next"]["title"], "review");
+        assert_eq!(json["next"]["content"], "notes");
+        assert_eq!(json["next"]["priority"], 2);
+        assert_eq!(json["next"]["done"], false);
+        assert_eq!(json["next"]["due_date"], due_date + week);
+        assert_eq!(json["next"]["tags"], serde_json::json!(["work"]));
+        assert_eq!(json["next"]["recurrence"], "FREQ=WEEKLY;COUNT=2");
+
+        //completing it again does not repeat twice
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":false}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert!(json.get("next").is_none());
+
+        //complete the second occurrence with a full update
+        let item_json = format!(r#"{{"content":"notes","creation_date":1,"done":true,"finish_date":60,"due_date":{},"id":2,"priority":2,"title":"review","tags":["work"],"recurrence":"FREQ=WEEKLY;COUNT=2"}}"#, due_date + week);
+        response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["next"]["due_date"], due_date + 2 * week);
+        assert_eq!(json["next"]["recurrence"], "FREQ=WEEKLY;COUNT=1");
+
+        //last occurrence, the series ends
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(3), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert!(json.get("next").is_none());
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 3);
+
+        //stop repeating with a patch
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(3), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"recurrence":"FREQ=DAILY"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(3), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"recurrence":null}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["recurrence"], Value::Null);
+
+        //recurring subtasks completed with their parent repeat too
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"release"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let item_json = format!(r#"{{"content":"","done":false,"due_date":{},"id":0,"priority":0,"title":"backup","parent_id":4,"recurrence":"FREQ=WEEKLY"}}"#, due_date);
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let params = UpdateParams { complete_children: Some(true) };
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(4), Query(params), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = get_todo(State(connection.clone()), Path(6)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["title"], "backup");
+        assert_eq!(json["item"]["done"], false);
+        assert_eq!(json["item"]["parent_id"], 4);
+        assert_eq!(json["item"]["due_date"], due_date + week);
+        assert_eq!(json["item"]["recurrence"], "FREQ=WEEKLY");
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        for (title, content) in [
+            ("Weekly review", "Prepare the notes"),
+            ("Deploy server", "Deploy the release and review the logs"),
+            ("Reviewer meeting", ""),
+            ("Discount", "100% off_sale"),
+        ] {
+            let item_json = format!(r#"{{"content":"{}","creation_date":1,"done":false,"finish_date":null,"due_date":null,"id":0,"priority":0,"title":"{}"}}"#, content, title);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let search = |search: &str, snippets: bool| QueryParams { search: Some(search.to_string()), snippets: Some(snippets), ..Default::default() };
+
+        //matches in the title rank first
+        let mut response = list_todos(State(connection.clone()), State(config.clone()), Query(search("review", false))).await.into_response();
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert_eq!(json["items"][0]["title"], "Weekly review");
+        assert_eq!(json["items"][1]["title"], "Deploy server");
+        assert!(json["items"][0].get("snippet").is_none());
+
+        //prefix
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search("rev*", false))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 3);
+
+        //phrase
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search(r#""review the logs""#, false))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["title"], "Deploy server");
+
+        //all words must match
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search("deploy notes", false))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 0);
+
+        //wildcards and operators of SQL or FTS5 are no special characters
+        for query in ["%", "_", "review OR deploy", "NOT", "\"", "off_sale"] {
+            response = list_todos(State(connection.clone()), State(config.clone()), Query(search(query, false))).await.into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+            body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            json = serde_json::from_slice(&body).unwrap();
+
+            let expected = match query {
+                "\"" => 4,
+                "off_sale" => 1,
+                _ => 0,
+            };
+            assert_eq!(json["items"].as_array().unwrap().len(), expected, "search {}", query);
+        }
+
+        //highlighted snippets
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search("logs", true))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert!(json["items"][0]["snippet"].as_str().unwrap().contains("<mark>logs</mark>"));
+
+        //the index follows updates and deletes
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"Monthly invoice"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search("review", false))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 0);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(search("invoice", false))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_nullable_dates() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        //zeros written before dates were nullable, as integer and as text
+        sqlx::query("
+                INSERT INTO todos (title, content, priority, creation_date, due_date, finish_date)
+                VALUES ('old integer', '', 0, 1, 0, 0), ('old text', '', 0, 2, '1970-01-01T00:00:00+00:00', '1970-01-01T00:00:00+00:00')
+            ")
+            .execute(&connection)
+            .await
+            .unwrap();
+        sqlx::raw_sql(include_str!("../migrations/0007_nullable_dates.sql")).execute(&connection).await.unwrap();
+
+        for id in [1, 2] {
+            let response = get_todo(State(connection.clone()), Path(id)).await.into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: Value = serde_json::from_slice(&body).unwrap();
+
+            assert_eq!(json["item"]["due_date"], Value::Null);
+            assert_eq!(json["item"]["finish_date"], Value::Null);
+        }
+
+        //missing dates are null
+        let item_json = r#"{"content":"","creation_date":3,"done":false,"id":0,"priority":0,"title":"no dates"}"#;
+        let mut response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        let mut body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["due_date"], Value::Null);
+
+        let item_json = r#"{"content":"","creation_date":4,"done":false,"finish_date":null,"due_date":2000000000,"id":0,"priority":0,"title":"due"}"#;
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["due_date"], 2000000000);
+        assert_eq!(json["item"]["finish_date"], Value::Null);
+
+        //todos without due date are sorted last by default
+        let sorted = |order: Order, nulls: Option<Nulls>| QueryParams { sort_by: Some(SortBy::DueDate), order: Some(order), nulls, ..Default::default() };
+
+        for order in [Order::Asc, Order::Desc] {
+            response = list_todos(State(connection.clone()), State(config.clone()), Query(sorted(order, None))).await.into_response();
+            body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            json = serde_json::from_slice(&body).unwrap();
+
+            assert_eq!(json["items"][0]["title"], "due");
+        }
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(sorted(Order::Asc, Some(Nulls::First)))).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["items"][3]["title"], "due");
+
+        //finish and reopen with a patch
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(4), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert!(json["item"]["finish_date"].is_i64());
+        assert_eq!(json["item"]["due_date"], 2000000000);
+
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(4), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":false,"due_date":null}"#)).await.into_response();
+        body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        json = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["item"]["finish_date"], Value::Null);
+        assert_eq!(json["item"]["due_date"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_filters() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+        let tomorrow = chrono::Utc::now().timestamp() + 24 * 60 * 60;
+
+        for (title, creation_date, due_date, done, finish_date, priority) in [
+            ("past open", 10, "1000".to_string(), false, "null", 1),
+            ("past done", 20, "1000".to_string(), true, "1500", 3),
+            ("future", 30, tomorrow.to_string(), false, "null", 2),
+            ("no due date", 40, "null".to_string(), false, "null", 0),
+        ] {
+            let item_json = format!(r#"{{"content":"","creation_date":{},"done":{},"finish_date":{},"due_date":null,"id":0,"priority":{},"title":"{}"}}"#, creation_date, done, finish_date, priority, title);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+
+            //the server sets the creation date and rejects due dates before it
+            sqlx::query("UPDATE todos SET creation_date = ?, due_date = ?, updated_at = ? WHERE title = ?")
+                .bind(chrono::DateTime::from_timestamp(creation_date, 0))
+                .bind(due_date.parse().ok().and_then(|due_date| chrono::DateTime::from_timestamp(due_date, 0)))
+                .bind(chrono::DateTime::from_timestamp(creation_date * 2, 0))
+                .bind(title)
+                .execute(&connection)
+                .await
+                .unwrap();
+        }
+
+        //titles of the todos matching the query string, sorted by creation date
+        let titles = async |uri: &str| -> Vec<String> {
+            let params: Query<QueryParams> = Query::try_from_uri(&uri.parse().unwrap()).unwrap();
+            let response = list_todos(State(connection.clone()), State(config.clone()), params).await.into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: Value = serde_json::from_slice(&body).unwrap();
+
+            json["items"].as_array().unwrap().iter().map(|item| item["title"].as_str().unwrap().to_string()).collect()
+        };
+
+        assert_eq!(titles("/todos?done=any&order=asc").await, ["past open", "past done", "future", "no due date"]);
+        assert_eq!(titles("/todos?done=true").await, ["past done"]);
+        assert_eq!(titles("/todos?done=false&order=asc").await, ["past open", "future", "no due date"]);
+
+        assert_eq!(titles("/todos?overdue=true").await, ["past open"]);
+        assert_eq!(titles("/todos?overdue=false&order=asc").await, ["past done", "future", "no due date"]);
+
+        assert_eq!(titles("/todos?due_before=2000&order=asc").await, ["past open", "past done"]);
+        assert_eq!(titles("/todos?due_after=2000").await, ["future"]);
+        assert_eq!(titles("/todos?due_after=1000&due_before=1001&order=asc").await, ["past open", "past done"]);
+        assert_eq!(titles("/todos?created_after=20&created_before=40&order=asc").await, ["past done", "future"]);
+        assert_eq!(titles("/todos?updated_since=60&order=asc").await, ["future", "no due date"]);
+        assert_eq!(titles("/todos?finished_after=1000").await, ["past done"]);
+        assert_eq!(titles("/todos?finished_before=1000").await, Vec::<String>::new());
+        assert_eq!(titles("/todos?priority_min=1&priority_max=2&order=asc").await, ["past open", "future"]);
+
+        //filters compose with sorting and pagination
+        assert_eq!(titles("/todos?done=any&priority_min=1&sort_by=priority&order=desc&count=2&offset=1").await, ["future", "past open"]);
+
+        //invalid values
+        assert!(Query::<QueryParams>::try_from_uri(&"/todos?done=maybe".parse().unwrap()).is_err());
+        assert!(Query::<QueryParams>::try_from_uri(&"/todos?due_before=tomorrow".parse().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        for i in 1..=5 {
+            let item_json = format!(r#"{{"content":"","creation_date":{},"done":{},"id":0,"priority":0,"title":"Item {}"}}"#, i, i % 2 == 0, i);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let page = async |uri: &str| -> Value {
+            let params: Query<QueryParams> = Query::try_from_uri(&uri.parse().unwrap()).unwrap();
+            let response = list_todos(State(connection.clone()), State(config.clone()), params).await.into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        //first page
+        let mut json = page("/todos?count=2&order=asc").await;
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+        assert_eq!(json["total"], 5);
+        assert_eq!(json["count"], 2);
+        assert_eq!(json["offset"], 0);
+        assert_eq!(json["prev"], Value::Null);
+        assert_eq!(json["next"], "/todos?count=2&offset=2&order=asc");
+
+        //follow the links
+        json = page(json["next"].as_str().unwrap()).await;
+        assert_eq!(json["items"][0]["title"], "Item 3");
+        assert_eq!(json["prev"], "/todos?count=2&offset=0&order=asc");
+
+        json = page(json["next"].as_str().unwrap()).await;
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(json["items"][0]["title"], "Item 5");
+        assert_eq!(json["next"], Value::Null);
+
+        //total respects filters and search
+        json = page("/todos?count=2&done=false&search=item").await;
+        assert_eq!(json["total"], 3);
+        assert_eq!(json["next"], "/todos?count=2&offset=2&done=false&search=item");
+
+        json = page("/todos?search=nothing").await;
+        assert_eq!(json["total"], 0);
+        assert_eq!(json["next"], Value::Null);
+
+        //offset past the end
+        json = page("/todos?count=2&offset=10").await;
+        assert_eq!(json["items"].as_array().unwrap().len(), 0);
+        assert_eq!(json["total"], 5);
+        assert_eq!(json["prev"], "/todos?count=2&offset=8");
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pagination() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        let add = async |title: &str, due_date: &str| {
+            let item_json = format!(r#"{{"content":"report","creation_date":1,"done":false,"due_date":{},"id":0,"priority":0,"title":"{}"}}"#, due_date, title);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        };
+
+        for (title, due_date) in [("a", "2000000100"), ("b", "2000000200"), ("c", "2000000200"), ("d", "null"), ("e", "null")] {
+            add(title, due_date).await;
+        }
+
+        let page = async |uri: &str| -> (StatusCode, Value) {
+            let params: Query<QueryParams> = Query::try_from_uri(&uri.parse().unwrap()).unwrap();
+            let response = list_todos(State(connection.clone()), State(config.clone()), params).await.into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice(&body).unwrap())
+        };
+        let titles = |json: &Value| -> Vec<String> {
+            json["items"].as_array().unwrap().iter().map(|item| item["title"].as_str().unwrap().to_string()).collect()
+        };
+
+        //first page
+        let (_, mut json) = page("/todos?count=2&sort_by=due_date&order=asc").await;
+        assert_eq!(titles(&json), ["a", "b"]);
+        let cursor = json["next_cursor"].as_str().unwrap().to_string();
+
+        //todos added and deleted before the cursor do not move the next page
+        add("before", "2000000050").await;
+        let response = delete_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        (_, json) = page(&format!("/todos?count=2&sort_by=due_date&order=asc&cursor={}", cursor)).await;
+        assert_eq!(titles(&json), ["c", "d"]);
+        assert_eq!(json["offset"], Value::Null);
+        assert_eq!(json["prev"], Value::Null);
+
+        //follow the link, the cursor is on a todo without due date
+        (_, json) = page(json["next"].as_str().unwrap()).await;
+        assert_eq!(titles(&json), ["e"]);
+        assert_eq!(json["next_cursor"], Value::Null);
+        assert_eq!(json["next"], Value::Null);
+
+        //descending with todos without due date first
+        let mut seen = Vec::new();
+        let mut uri = "/todos?count=2&sort_by=due_date&order=desc&nulls=first".to_string();
+        loop {
+            (_, json) = page(&uri).await;
+            seen.extend(titles(&json));
+            match json["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/todos?count=2&sort_by=due_date&order=desc&nulls=first&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, ["e", "d", "c", "b", "before"]);
+
+        //search results ordered by relevance
+        seen.clear();
+        uri = "/todos?count=1&search=report".to_string();
+        loop {
+            (_, json) = page(&uri).await;
+            seen.extend(titles(&json));
+            match json["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/todos?count=1&search=report&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), 5);
+
+        //cursor of another sorting or no cursor at all
+        let (status, _) = page(&format!("/todos?count=2&sort_by=priority&cursor={}", cursor)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, _) = page("/todos?cursor=not-a-cursor").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_sort() {
+        let connection = setup_test_db().await;
+        run_migrations(&connection).await.unwrap();
+        let config = Arc::new(Config::default());
+
+        for (title, priority, due_date) in [("b", 1, "2000000200"), ("a", 2, "2000000300"), ("C", 1, "2000000100"), ("d", 1, "null"), ("c", 2, "2000000300")] {
+            let item_json = format!(r#"{{"content":"","creation_date":1,"done":false,"due_date":{},"id":0,"priority":{},"title":"{}"}}"#, due_date, priority, title);
+            let response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let page = async |uri: &str| -> Value {
+            let params: Query<QueryParams> = Query::try_from_uri(&uri.parse().unwrap()).unwrap();
+            let response = list_todos(State(connection.clone()), State(config.clone()), params).await.into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+        let titles = |json: &Value| -> Vec<String> {
+            json["items"].as_array().unwrap().iter().map(|item| item["title"].as_str().unwrap().to_string()).collect()
+        };
+
+        //several keys, title ignores case
+        let mut json = page("/todos?sort=-priority,due_date,title").await;
+        assert_eq!(titles(&json), ["a", "c", "C", "b", "d"]);
+
+        //same creation date, the ID decides
+        json = page("/todos?sort=creation_date").await;
+        assert_eq!(titles(&json), ["b", "a", "C", "d", "c"]);
+        json = page("/todos?sort=-creation_date").await;
+        assert_eq!(titles(&json), ["c", "d", "C", "a", "b"]);
+
+        //sort replaces sort_by and order
+        json = page("/todos?sort=-id&sort_by=priority&order=asc").await;
+        assert_eq!(titles(&json), ["c", "d", "C", "a", "b"]);
+
+        //the server sets the update time
+        json = page("/todos?sort=updated_at,id").await;
+        assert!(json["items"][0]["updated_at"].as_i64().unwrap() > 1);
+
+        //page through several keys with a cursor
+        let mut seen = Vec::new();
+        let mut uri = "/todos?count=2&sort=-priority,due_date,title".to_string();
+        loop {
+            json = page(&uri).await;
+            seen.extend(titles(&json));
+            match json["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/todos?count=2&sort=-priority,due_date,title&cursor={}", cursor),
+                None => break,
+            }
+            assert!(json["next"].as_str().unwrap().contains("sort=-priority%2Cdue_date%2Ctitle"));
+        }
+        assert_eq!(seen, ["a", "c", "C", "b", "d"]);
+
+        //unknown fields are rejected
+        assert!(Query::<QueryParams>::try_from_uri(&"/todos?sort=-unknown".parse().unwrap()).is_err());
+        assert!(Query::<QueryParams>::try_from_uri(&"/todos?sort=,".parse().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_versions() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+
+        let if_match = |etag: &str| -> HeaderMap {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_MATCH, etag.parse().unwrap());
+            headers
+        };
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        //GET sends the version as ETag
+        let mut response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], "\"1\"");
+        assert_eq!(json(response).await["item"]["version"], 1);
+
+        //first teammate saves with the matching version
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), if_match("\"1\""), Bytes::from(r#"{"title":"first"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], "\"2\"");
+
+        //second teammate still has version 1
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), if_match("\"1\""), Bytes::from(r#"{"title":"second"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        let item_json = r#"{"content":"","creation_date":0,"done":false,"due_date":null,"id":0,"priority":0,"title":"second"}"#;
+        response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), if_match("\"1\""), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        response = delete_todo(State(connection.clone()), Path(1), if_match("\"1\"")).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        //nothing got overwritten
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(json(response).await["item"]["title"], "first");
+
+        //one of several tags or any version match
+        response = update_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), if_match("\"1\", \"2\""), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["item"]["version"], 3);
+
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(1), Query(UpdateParams::default()), if_match("*"), Bytes::from(r#"{"title":"third"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //a missing todo is still not found
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(100), Query(UpdateParams::default()), if_match("\"1\""), Bytes::from(r#"{"title":"none"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //delete the current version
+        response = delete_todo(State(connection.clone()), Path(1), if_match("\"4\"")).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_toggle() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+        let before = chrono::Utc::now().timestamp();
+
+        //complete sets the finish date with the time of the server
+        let mut response = complete_todo(State(connection.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], "\"2\"");
+        let mut item = json(response).await["item"].take();
+        assert_eq!(item["done"], true);
+        assert!(item["finish_date"].as_i64().unwrap() >= before);
+
+        //completing again keeps the first finish date
+        response = complete_todo(State(connection.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let again = json(response).await["item"].take();
+        assert_eq!(again["finish_date"], item["finish_date"]);
+        assert_eq!(again["version"], 2);
+
+        //reopen removes the finish date
+        response = reopen_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        item = json(response).await["item"].take();
+        assert_eq!(item["done"], false);
+        assert_eq!(item["finish_date"], Value::Null);
+
+        response = reopen_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //toggle switches in both directions
+        response = toggle_todo(State(connection.clone()), Path(2), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        item = json(response).await["item"].take();
+        assert_eq!(item["done"], true);
+        assert!(item["finish_date"].as_i64().unwrap() >= before);
+
+        response = toggle_todo(State(connection.clone()), Path(2), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        item = json(response).await["item"].take();
+        assert_eq!(item["done"], false);
+        assert_eq!(item["finish_date"], Value::Null);
+
+        //outdated version and unknown todo
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_MATCH, "\"1\"".parse().unwrap());
+        response = toggle_todo(State(connection.clone()), Path(1), Query(UpdateParams::default()), headers.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        response = reopen_todo(State(connection.clone()), Path(1), headers).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        response = toggle_todo(State(connection.clone()), Path(100), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = complete_todo(State(connection.clone()), Path(100), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //completing a recurring todo creates the next occurrence
+        response = patch_todo(State(connection.clone()), State(Arc::new(Config::default())), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"recurrence":"FREQ=DAILY"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = complete_todo(State(connection.clone()), Path(2), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["next"]["done"], false);
+        assert_eq!(body["next"]["recurrence"], "FREQ=DAILY");
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::from_args(ConfigArgs { max_title_length: Some(10), max_content_length: Some(20), max_priority: Some(3), ..Default::default() }).unwrap());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        //every invalid field is listed
+        let item_json = r#"{"content":"far too long content for the limit","done":false,"due_date":1,"id":0,"priority":-1,"title":"  ","tags":["a,b"]}"#;
+        let mut response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = json(response).await;
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["errors"], serde_json::json!([
+            {"field": "title", "message": "must not be empty"},
+            {"field": "content", "message": "must not be longer than 20 characters"},
+            {"field": "priority", "message": "must be between 0 and 3"},
+            {"field": "due_date", "message": "must not be earlier than the creation date"},
+            {"field": "tags", "message": "must not contain a comma"},
+        ]));
+        assert!(body["message"].as_str().unwrap().starts_with("Invalid fields: title must not be empty, content"));
+
+        //limits come from the config
+        let item_json = r#"{"content":"","done":false,"id":0,"priority":5,"title":"long title"}"#;
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(json(response).await["errors"], serde_json::json!([{"field": "priority", "message": "must be between 0 and 3"}]));
+        response = add_todo(State(connection.clone()), State(Arc::new(Config::default())), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        //the due date is compared with the stored creation date
+        let item_json = r#"{"content":"","done":false,"due_date":1,"id":0,"priority":0,"title":"Test2"}"#;
+        response = update_todo(State(connection.clone()), State(config.clone()), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(json(response).await["errors"][0]["field"], "due_date");
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(2), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"due_date":1}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //a patch only checks the fields it contains
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"eleven char"}"#)).await.into_response();
+        assert_eq!(json(response).await["errors"], serde_json::json!([{"field": "title", "message": "must not be longer than 10 characters"}]));
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"due_date":2,"priority":3}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(100), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"missing"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //the next occurrence of a todo completed late is due before it was created, it can still be updated
+        sqlx::query("INSERT INTO todos (title, creation_date, due_date, recurrence) VALUES (?, ?, ?, ?)")
+            .bind("late")
+            .bind(10)
+            .bind(20)
+            .bind("FREQ=DAILY")
+            .execute(&connection)
+            .await
+            .unwrap();
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(4), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"done":true}"#)).await.into_response();
+        let next = json(response).await["next"].clone();
+        assert_eq!(next["due_date"], 20 + 24 * 60 * 60);
+
+        let item_json = format!(r#"{{"content":"","done":false,"due_date":{},"id":0,"priority":0,"title":"later","recurrence":"FREQ=DAILY"}}"#, next["due_date"]);
+        response = update_todo(State(connection.clone()), State(config.clone()), Path(next["id"].as_i64().unwrap()), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //moving it to another date in the past is checked
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(next["id"].as_i64().unwrap()), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"due_date":30}"#)).await.into_response();
+        assert_eq!(json(response).await["errors"], serde_json::json!([{"field": "due_date", "message": "must not be earlier than the creation date"}]));
+
+        //recurrence, project and parent are listed with the other fields
+        let item_json = r#"{"content":"","done":false,"id":0,"priority":9,"title":"refs","recurrence":"FREQ=SOMETIMES","project_id":100,"parent_id":100}"#;
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(item_json)).await.into_response();
+        let fields: Vec<Value> = json(response).await["errors"].as_array().unwrap().iter().map(|error| error["field"].clone()).collect();
+        assert_eq!(fields, vec!["priority", "recurrence", "project_id", "parent_id"]);
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"parent_id":1}"#)).await.into_response();
+        assert_eq!(json(response).await["errors"], serde_json::json!([{"field": "parent_id", "message": "must not be the todo itself or one of its subtasks"}]));
+
+        //negative maximum priority
+        assert!(matches!(Config::from_args(ConfigArgs { max_priority: Some(-1), ..Default::default() }), Err(ConfigError::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_bulk() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        //all operations are applied in order
+        let bulk_json = r#"{"operations":[
+            {"op":"create","item":{"content":"","done":false,"id":0,"priority":0,"title":"Bulk"}},
+            {"op":"update","id":2,"patch":{"priority":2},"version":1},
+            {"op":"toggle","id":1}
+        ]}"#;
+        let mut response = bulk_todos(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(bulk_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["op"], "create");
+        assert_eq!(results[0]["item"]["title"], "Bulk");
+        assert_eq!(results[1]["item"]["priority"], 2);
+        assert_eq!(results[1]["item"]["version"], 2);
+        assert_eq!(results[2]["id"], 1);
+        assert_eq!(results[2]["item"]["done"], true);
+
+        //a failed operation rolls back the ones before
+        let bulk_json = r#"{"operations":[{"op":"delete","id":1},{"op":"update","id":100,"patch":{"title":"missing"}}]}"#;
+        response = bulk_todos(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(bulk_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(json(response).await["index"], 1);
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //versions and validation are checked like for single todos
+        let bulk_json = r#"{"operations":[{"op":"toggle","id":2,"version":1}]}"#;
+        response = bulk_todos(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(bulk_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        let bulk_json = r#"{"operations":[{"op":"delete","id":2},{"op":"create","item":{"content":"","done":false,"id":0,"priority":0,"title":""}}]}"#;
+        response = bulk_todos(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(bulk_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = json(response).await;
+        assert_eq!(body["index"], 1);
+        assert_eq!(body["errors"][0]["field"], "title");
+        assert_eq!(body["message"], "Operation 1 failed: Invalid fields: title must not be empty");
+
+        //number of operations is limited
+        let small = Arc::new(Config::from_args(ConfigArgs { max_bulk_operations: Some(1), ..Default::default() }).unwrap());
+        let bulk_json = r#"{"operations":[{"op":"toggle","id":1},{"op":"toggle","id":2}]}"#;
+        response = bulk_todos(State(connection.clone()), State(small), HeaderMap::new(), Bytes::from(bulk_json)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        //deleting by filter requires a filter
+        response = delete_todos(State(connection.clone()), Query(QueryParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let params = QueryParams { done: Some(DoneFilter::Done), ..Default::default() };
+        response = delete_todos(State(connection.clone()), Query(params), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["deleted"], 2);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["items"][0]["title"], "Bulk");
+    }
+
+    #[tokio::test]
+    async fn test_trash() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        //deleted todos are hidden but kept in the trash
+        let mut response = delete_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = get_todo(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = delete_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"trashed"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = list_todos(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        assert_eq!(json(response).await["total"], 1);
+
+        response = list_trash(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["items"][0]["id"], 1);
+        assert_eq!(body["items"][0]["version"], 2);
+        assert!(body["items"][0]["deleted_at"].is_i64());
+
+        //subtasks are deleted and restored together with their parent
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Parent"}"#)).await.into_response();
+        let parent_id = json(response).await["item"]["id"].as_i64().unwrap();
+        let child_json = format!(r#"{{"content":"","done":false,"id":0,"priority":0,"title":"Child","parent_id":{}}}"#, parent_id);
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(child_json)).await.into_response();
+        let child_id = json(response).await["item"]["id"].as_i64().unwrap();
+
+        response = delete_todo(State(connection.clone()), Path(parent_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = get_todo(State(connection.clone()), Path(child_id)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = restore_todo(State(connection.clone()), Path(child_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        response = restore_todo(State(connection.clone()), Path(parent_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert!(body["item"]["deleted_at"].is_null());
+        assert_eq!(body["item"]["children_total"], 1);
+        response = get_todo(State(connection.clone()), Path(child_id)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = restore_todo(State(connection.clone()), Path(parent_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //purging deletes for good
+        response = purge_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = purge_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = restore_todo(State(connection.clone()), Path(1), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //a subtask whose deletion was undone is purged with its parent and clients learn about it
+        let mut session = HeaderMap::new();
+        session.insert("x-session-id", "desktop".parse().unwrap());
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Old"}"#)).await.into_response();
+        let old_id = json(response).await["item"]["id"].as_i64().unwrap();
+        let child_json = format!(r#"{{"content":"","done":false,"id":0,"priority":0,"title":"Old child","parent_id":{}}}"#, old_id);
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(child_json)).await.into_response();
+        let old_child_id = json(response).await["item"]["id"].as_i64().unwrap();
+
+        response = delete_todo(State(connection.clone()), Path(old_child_id), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_todo(State(connection.clone()), Path(old_id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = undo(State(connection.clone()), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = sync_todos(State(connection.clone()), Query(SyncParams::default())).await.into_response();
+        let token = json(response).await["token"].as_i64().unwrap();
+        sqlx::query("UPDATE todos SET deleted_at = 1 WHERE id = ?").bind(old_id).execute(&connection).await.unwrap();
+        assert_eq!(purge_expired(&connection, 30).await.unwrap(), 2);
+
+        response = sync_todos(State(connection.clone()), Query(SyncParams { since: Some(token) })).await.into_response();
+        let tombstones: Vec<i64> = json(response).await["tombstones"].as_array().unwrap().iter().map(|tombstone| tombstone["id"].as_i64().unwrap()).collect();
+        assert_eq!(tombstones, vec![old_id, old_child_id]);
+
+        //todos are purged after the retention period
+        response = delete_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(purge_expired(&connection, 30).await.unwrap(), 0);
+        sqlx::query("UPDATE todos SET deleted_at = 1 WHERE id = 2").execute(&connection).await.unwrap();
+        assert_eq!(purge_expired(&connection, 30).await.unwrap(), 1);
+
+        //deleting by filter moves todos to the trash
+        let params = QueryParams { search: Some("Parent".to_string()), ..Default::default() };
+        response = delete_todos(State(connection.clone()), Query(params), HeaderMap::new()).await.into_response();
+        assert_eq!(json(response).await["deleted"], 2);
+
+        let params = QueryParams { trashed: Some(true), done: Some(DoneFilter::Open), ..Default::default() };
+        response = delete_todos(State(connection.clone()), Query(params), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        response = purge_trash(State(connection.clone()), HeaderMap::new()).await.into_response();
+        assert_eq!(json(response).await["deleted"], 2);
+        response = list_trash(State(connection.clone()), State(config.clone()), Query(QueryParams::default())).await.into_response();
+        assert_eq!(json(response).await["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        let mut alice = HeaderMap::new();
+        alice.insert("x-actor", "alice".parse().unwrap());
+
+        //todos without recorded changes have an empty history
+        let mut response = todo_history(State(connection.clone()), Path(1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["items"].as_array().unwrap().len(), 0);
+        response = todo_history(State(connection.clone()), Path(100)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = add_todo(State(connection.clone()), State(config.clone()), alice.clone(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Tracked","tags":["work"]}"#)).await.into_response();
+        let id = json(response).await["item"]["id"].as_i64().unwrap();
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(id), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"Renamed"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //unchanged todos are not recorded
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(id), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"Renamed"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = toggle_todo(State(connection.clone()), Path(id), Query(UpdateParams::default()), alice.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_todo(State(connection.clone()), Path(id), alice.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = todo_history(State(connection.clone()), Path(id)).await.into_response();
+        let body = json(response).await;
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 4);
+
+        assert_eq!(items[0]["operation"], "create");
+        assert_eq!(items[0]["actor"], "alice");
+        assert!(items[0]["before"].is_null());
+        assert_eq!(items[0]["after"]["tags"][0], "work");
+
+        //only changed fields are part of an event
+        assert_eq!(items[1]["operation"], "update");
+        assert_eq!(items[1]["actor"], "anonymous");
+        assert_eq!(items[1]["before"], serde_json::json!({"title": "Tracked"}));
+        assert_eq!(items[1]["after"], serde_json::json!({"title": "Renamed"}));
+
+        assert_eq!(items[2]["operation"], "toggle");
+        assert_eq!(items[2]["before"]["done"], false);
+        assert_eq!(items[2]["after"]["done"], true);
+        assert!(items[2]["after"]["finish_date"].is_i64());
+
+        assert_eq!(items[3]["operation"], "delete");
+        assert!(items[3]["after"]["deleted_at"].is_i64());
+        assert_eq!(items[3]["version"], 5);
+
+        //the history outlives the todo
+        response = purge_todo(State(connection.clone()), Path(id), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = todo_history(State(connection.clone()), Path(id)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 5);
+        assert_eq!(body["items"][4]["operation"], "purge");
+        assert!(body["items"][4]["after"].is_null());
+
+        //the global feed is filtered and paginated
+        let params = EventParams { actor: Some("alice".to_string()), ..Default::default() };
+        response = list_events(State(connection.clone()), State(config.clone()), Query(params)).await.into_response();
+        assert_eq!(json(response).await["items"].as_array().unwrap().len(), 3);
+
+        let params = EventParams { operation: Some(Operation::Delete), ..Default::default() };
+        response = list_events(State(connection.clone()), State(config.clone()), Query(params)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 1);
+        assert_eq!(body["items"][0]["todo_id"], id);
+
+        let params = EventParams { count: Some(2), ..Default::default() };
+        response = list_events(State(connection.clone()), State(config.clone()), Query(params)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        assert_eq!(body["last_id"], 2);
+        assert_eq!(body["next"], "/events?count=2&after_id=2");
+
+        let params = EventParams { after_id: Some(5), ..Default::default() };
+        response = list_events(State(connection.clone()), State(config.clone()), Query(params)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 0);
+        assert_eq!(body["last_id"], 5);
+        assert!(body["next"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_revert_and_undo() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        let mut session = HeaderMap::new();
+        session.insert("x-session-id", "desktop".parse().unwrap());
+
+        let revert = |id: i64, revision: i64| revert_todo(State(connection.clone()), Path(id), Query(RevertParams { revision }), HeaderMap::new());
+
+        //todos are reverted to the state of a revision
+        let mut response = add_todo(State(connection.clone()), State(config.clone()), session.clone(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Draft","tags":["work"]}"#)).await.into_response();
+        let id = json(response).await["item"]["id"].as_i64().unwrap();
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(id), Query(UpdateParams::default()), session.clone(), Bytes::from(r#"{"title":"Final","tags":[]}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = revert(id, 1).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert_eq!(body["item"]["title"], "Draft");
+        assert_eq!(body["item"]["tags"][0], "work");
+        assert_eq!(body["item"]["version"], 3);
+
+        response = todo_history(State(connection.clone()), Path(id)).await.into_response();
+        assert_eq!(json(response).await["items"][2]["operation"], "revert");
+
+        response = revert(id, 4).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = revert(100, 1).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //the state before the first recorded change is known too
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"changed"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = revert(1, 1).await.into_response();
+        assert_eq!(json(response).await["item"]["title"], "Test1");
+
+        //changes made by someone else since are not undone
+        response = undo(State(connection.clone()), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(id), Query(UpdateParams::default()), session.clone(), Bytes::from(r#"{"priority":2}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = undo(State(connection.clone()), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert_eq!(body["operation"], "update");
+        assert_eq!(body["items"][0]["priority"], 0);
+        assert_eq!(body["items"][0]["title"], "Draft");
+
+        //an edit that also completes the todo is undone as a whole
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(id), Query(UpdateParams::default()), session.clone(), Bytes::from(r#"{"title":"Done","done":true}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = undo(State(connection.clone()), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert_eq!(body["items"][0]["title"], "Draft");
+        assert_eq!(body["items"][0]["done"], false);
+        assert_eq!(body["items"][0]["finish_date"], Value::Null);
+
+        //deleted todos are restored with their subtasks
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Parent"}"#)).await.into_response();
+        let parent_id = json(response).await["item"]["id"].as_i64().unwrap();
+        let child_json = format!(r#"{{"content":"","done":false,"id":0,"priority":0,"title":"Child","parent_id":{}}}"#, parent_id);
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(child_json)).await.into_response();
+        let child_id = json(response).await["item"]["id"].as_i64().unwrap();
+
+        response = delete_todo(State(connection.clone()), Path(parent_id), session.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = undo(State(connection.clone()), session.clone()).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["operation"], "delete");
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        response = get_todo(State(connection.clone()), Path(child_id)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //created todos are moved to the trash, every session undoes its own changes
+        let mut other = HeaderMap::new();
+        other.insert("x-session-id", "other".parse().unwrap());
+        response = add_todo(State(connection.clone()), State(config.clone()), other.clone(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Mistake"}"#)).await.into_response();
+        let mistake_id = json(response).await["item"]["id"].as_i64().unwrap();
+
+        response = undo(State(connection.clone()), other.clone()).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["operation"], "create");
+        assert_eq!(body["items"].as_array().unwrap().len(), 0);
+        response = get_todo(State(connection.clone()), Path(mistake_id)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        response = undo(State(connection.clone()), other.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        response = undo(State(connection.clone()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        use futures_util::StreamExt;
+
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        //streams share a single task that checks for new changes
+        let notifications = notification_channel();
+        tokio::spawn(watch_events(connection.clone(), notifications.clone()));
+
+        //read events until the stream has sent `count` of them
+        let read = async |response: axum::response::Response, count: usize| -> Vec<String> {
+            let mut body = response.into_body().into_data_stream();
+            let mut events = Vec::new();
+            while events.len() < count {
+                let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+                let text = String::from_utf8(chunk.to_vec()).unwrap();
+                events.extend(text.split("\n\n").filter(|event| event.contains("id:")).map(str::to_string));
+            }
+            events
+        };
+
+        let mut response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"Live"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response = toggle_todo(State(connection.clone()), Path(3), Query(UpdateParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_todo(State(connection.clone()), Path(3), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = restore_todo(State(connection.clone()), Path(3), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //all changes after the given event are sent in order
+        let params = StreamParams { after_id: Some(0) };
+        response = stream_events(State(connection.clone()), State(notifications.clone()), Query(params), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let events = read(response, 4).await;
+        assert!(events[0].contains("event: created") && events[0].contains("id: 1"));
+        assert!(events[1].contains("event: toggled") && events[1].contains(r#""done":true"#));
+        assert!(events[2].contains("event: deleted") && events[2].contains(r#""operation":"delete""#));
+        assert!(events[3].contains("event: created") && events[3].contains(r#""operation":"restore""#));
+
+        //the header resumes the stream
+        let mut headers = HeaderMap::new();
+        headers.insert("last-event-id", "3".parse().unwrap());
+        response = stream_events(State(connection.clone()), State(notifications.clone()), Query(StreamParams::default()), headers).await.into_response();
+        let events = read(response, 1).await;
+        assert!(events[0].contains("id: 4"));
+
+        //without a start only new changes are sent, to every open stream
+        response = stream_events(State(connection.clone()), State(notifications.clone()), Query(StreamParams::default()), HeaderMap::new()).await.into_response();
+        let other = stream_events(State(connection.clone()), State(notifications.clone()), Query(StreamParams::default()), HeaderMap::new()).await.into_response();
+        let patched = patch_todo(State(connection.clone()), State(config.clone()), Path(3), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"Changed"}"#)).await.into_response();
+        assert_eq!(patched.status(), StatusCode::OK);
+        let events = read(response, 1).await;
+        assert!(events[0].contains("event: updated") && events[0].contains("id: 5"));
+        assert_eq!(read(other, 1).await, events);
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let connection = setup_test_db().await;
+        populate_test_db(connection.clone()).await;
+        let config = Arc::new(Config::default());
+
+        let json = async |response: axum::response::Response| -> Value {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+
+        let sync = |since: Option<i64>| sync_todos(State(connection.clone()), Query(SyncParams { since }));
+        let ids = |list: &Value| list.as_array().unwrap().iter().map(|item| item["id"].as_i64().unwrap()).collect::<Vec<_>>();
+
+        //the first sync returns all todos
+        let mut response = sync(None).await.into_response();
+        let body = json(response).await;
+        assert_eq!(ids(&body["items"]), vec![1, 2]);
+        assert_eq!(body["tombstones"].as_array().unwrap().len(), 0);
+        let token = body["token"].as_i64().unwrap();
+
+        response = add_todo(State(connection.clone()), State(config.clone()), HeaderMap::new(), Bytes::from(r#"{"content":"","done":false,"id":0,"priority":0,"title":"New","tags":["home"]}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"first"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(1), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(r#"{"title":"second"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //changed todos are returned once in their current state, deleted todos as tombstones
+        response = sync(Some(token)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(ids(&body["items"]), vec![1, 3]);
+        assert_eq!(body["items"][0]["title"], "second");
+        assert_eq!(ids(&body["tombstones"]), vec![2]);
+        assert!(body["tombstones"][0]["deleted_at"].is_i64());
+        let token = body["token"].as_i64().unwrap();
+
+        response = sync(Some(token)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 0);
+        assert_eq!(body["token"], token);
+
+        //purged todos and changes made through tags and projects are part of the sequence
+        response = purge_todo(State(connection.clone()), Path(2), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = update_tag(State(connection.clone()), Path(1), HeaderMap::new(), Bytes::from(r#"{"name":"house"}"#)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = sync(Some(token)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(ids(&body["tombstones"]), vec![2]);
+        assert_eq!(ids(&body["items"]), vec![3]);
+        assert_eq!(body["items"][0]["tags"][0], "house");
+        let token = body["token"].as_i64().unwrap();
+
+        response = add_project(State(connection.clone()), Bytes::from(r#"{"name":"Errands"}"#)).await.into_response();
+        let project_id = json(response).await["item"]["id"].as_i64().unwrap();
+        let patch = format!(r#"{{"project_id":{}}}"#, project_id);
+        response = patch_todo(State(connection.clone()), State(config.clone()), Path(3), Query(UpdateParams::default()), HeaderMap::new(), Bytes::from(patch)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        response = delete_project(State(connection.clone()), Path(project_id), Query(DeleteProjectParams::default()), HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response = sync(Some(token)).await.into_response();
+        let body = json(response).await;
+        assert_eq!(ids(&body["items"]), vec![3]);
+        assert!(body["items"][0]["project_id"].is_null());
+
+        response = sync(Some(body["token"].as_i64().unwrap() + 1)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
-}
\ No newline at end of file
+}
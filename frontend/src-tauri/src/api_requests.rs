@@ -1,16 +1,29 @@
 //Jakob Frenzel
 //05/01/25
 
-use tick_backend::data_structs::{ TodoItem, QueryParams };
-use serde::Deserialize;
-use chrono::{ Utc, TimeZone };
+use tick_backend::data_structs::{ BulkOperation, BulkRequest, DoneFilter, SyncParams, TodoEvent, TodoItem, TodoNotification, TodoPatch, Tombstone, QueryParams };
+use tick_backend::history::{ ACTOR_HEADER, SESSION_HEADER };
+use std::sync::{ Mutex, OnceLock };
+use std::time::Duration;
+use tauri::{ AppHandle, Emitter };
+use tick_backend::validation::FieldError;
+use serde::{ Deserialize, Serialize, de::DeserializeOwned };
+
+/// Error shown if a todo was changed by someone else since it was loaded
+const CONFLICT_MESSAGE: &str = "This todo was changed by someone else in the meantime. Reload it and apply your changes again.";
+
+/// Session of this app run, the backend undoes the changes of a session
+static SESSION: OnceLock<String> = OnceLock::new();
+
+/// Backend the app follows the changes of, see [`subscribe_events()`]
+static STREAM_URL: Mutex<Option<String>> = Mutex::new(None);
+
+/// Time to wait before the event stream is opened again after it was closed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
 /// Struct that contains a response the API can send
 #[derive(Deserialize)]
 struct ApiResponse<T> {
-    /// Status of the API response, eg "OK"
-    status: String,
-
     /// response can contain a list of items
     items: Option<T>,
 
@@ -19,15 +32,124 @@ struct ApiResponse<T> {
 
     /// repsonse can contain a message, eg error message
     message: Option<String>,
+
+    /// validation errors contain the invalid fields
+    errors: Option<Vec<FieldError>>,
+
+    /// list responses contain the number of items on all pages
+    total: Option<i64>,
+
+    /// list responses contain a cursor to the next page if there is one
+    next_cursor: Option<String>,
+
+    /// bulk responses contain a result for every operation
+    results: Option<Vec<serde_json::Value>>,
+
+    /// responses of deletions by filter contain the number of deleted todos
+    deleted: Option<u64>,
+
+    /// sync responses contain the deleted todos
+    tombstones: Option<Vec<Tombstone>>,
+
+    /// sync responses contain the token of the next sync
+    token: Option<i64>,
+}
+
+/// Struct that contains a page of todo items
+#[derive(Serialize)]
+pub struct TodoPage {
+    /// todo items on this page
+    items: Vec<TodoItem>,
+
+    /// number of todo items matching the query on all pages
+    total: i64,
+
+    /// cursor to request the next page with, `None` on the last page
+    next_cursor: Option<String>,
+}
+
+/// Create a client for requests to the backend.
+/// 
+/// Every request names the user of the operating system in the `X-Actor` header, so the history of a todo shows who changed it.
+/// The `X-Session-Id` header is the same for all requests until the app is closed, see [`undo()`].
+fn client() -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+
+    //process ID and start time tell app runs apart
+    let session = SESSION.get_or_init(|| format!("{}-{}", std::process::id(), chrono::Utc::now().timestamp_millis()));
+    if let Ok(value) = reqwest::header::HeaderValue::from_str(session) {
+        headers.insert(SESSION_HEADER, value);
+    }
+
+    //USER on linux and macOS, USERNAME on windows
+    let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok();
+    if let Some(value) = user.and_then(|user| reqwest::header::HeaderValue::from_str(&user).ok()) {
+        headers.insert(ACTOR_HEADER, value);
+    }
+
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Struct that contains the changes since the last sync
+#[derive(Serialize)]
+pub struct SyncResult {
+    /// todo items created or changed since the last sync
+    items: Vec<TodoItem>,
+
+    /// todo items deleted since the last sync
+    tombstones: Vec<Tombstone>,
+
+    /// token to pass to the next sync
+    token: i64,
+}
+
+/// Parse the body of a response from the backend.
+/// 
+/// The HTTP status code decides if the request was successful. On error the message sent by the backend is returned, or the status code if the body holds no message. If the backend rejected invalid fields, each field and the reason is returned on its own line.
+/// 
+/// * `response` - Response received from the backend
+async fn parse_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<ApiResponse<T>, String> {
+    let status = response.status();
+
+    //get raw response body
+    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+
+    if !status.is_success() {
+        //error body might not be json, eg from a proxy in between
+        let parsed = serde_json::from_str::<ApiResponse<T>>(&raw_body).ok();
+
+        if let Some(errors) = parsed.as_ref().and_then(|parsed| parsed.errors.as_ref()) {
+            let fields: Vec<String> = errors
+                .iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect();
+
+            return Err(fields.join("\n"));
+        }
+
+        let message = parsed.and_then(|parsed| parsed.message);
+
+        return Err(message.unwrap_or_else(|| format!("Unexpected status: {}", status)));
+    }
+
+    //parse JSON from reponse body
+    serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })
 }
 
 /// Fetch todos from the backend.
 /// 
+/// The todo items include their tags. Filter by tags with the `tag`, `any_tag` and `all_tags` fields of `params`.
+/// Returns the requested page and the total number of todo items, so the number of pages is known.
+/// The page also holds the cursor to the next page, pass it as `cursor` in `params` to page without skipping or repeating todo items.
+/// 
 /// * `params` - Query that is applied at the backend
 /// * `api_url` - URL to the backend server
 #[tauri::command]
-pub async fn fetch_todos(params: QueryParams, api_url: String) -> Result<Vec<TodoItem>, String> {
-    let client = reqwest::Client::new();
+pub async fn fetch_todos(params: QueryParams, api_url: String) -> Result<TodoPage, String> {
+    let client = client();
     let url = format!("{}/todos", api_url);
 
     let response = client
@@ -37,238 +159,442 @@ pub async fn fetch_todos(params: QueryParams, api_url: String) -> Result<Vec<Tod
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    //get raw response body
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+    let parsed: ApiResponse<Vec<TodoItem>> = parse_response(response).await?;
 
-    //parse JSON from reponse body
-    let parsed: ApiResponse<Vec<TodoItem>> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    let items = parsed.items.unwrap_or_default();
+    let total = parsed.total.unwrap_or(items.len() as i64);
 
-    match parsed.status.as_str() {
-        "ok" => {
-            let items = parsed.items.unwrap_or_default();
-            Ok(items)
-        }
-        "error" => {
-            let msg = parsed.message.unwrap_or("Unknown error".into());
-            Err(msg)
-        }
-        other => {
-            Err(format!("Unexpected status: {}", other))
-        }
-    }
+    Ok(TodoPage { items, total, next_cursor: parsed.next_cursor })
 }
 
 /// Toggle the status of a specific todo item.
 /// 
-/// Fetches todo item from backend. Toggles the done value. Updates the todo to the backend. If the status is toggled to false the done date is set to `0`. If the status is toggled to true the done date is set to `Utc::now()`.
+/// Completes the todo if it is shown as not done and reopens it otherwise. The backend sets or removes the done date with its own time. Returns the updated todo item.
 /// 
 /// * `id` - ID of the todo item to toggle
+/// * `done` - Current status of the todo item as shown in the app
 /// * `api_url` - URL to the backend server
 #[tauri::command]
-pub async fn toggle_todo_status(id: i64, api_url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn toggle_todo_status(id: i64, done: bool, api_url: String) -> Result<TodoItem, String> {
+    let client = client();
 
-    //get the todo based on id
-    let url = format!("{}/todos/{}", api_url, id);
+    set_done(&client, &api_url, id, !done).await
+}
+
+/// Complete or reopen a todo item at the backend.
+/// 
+/// * `client` - Client to send the request with
+/// * `api_url` - URL to the backend server
+/// * `id` - ID of the todo item
+/// * `done` - New status of the todo item
+async fn set_done(client: &reqwest::Client, api_url: &str, id: i64, done: bool) -> Result<TodoItem, String> {
+    let url = format!("{}/todos/{}/{}", api_url, id, if done { "complete" } else { "reopen" });
 
     let response = client
-        .get(&url)
+        .post(&url)
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
-
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
 
-    let mut todo: TodoItem = match parsed.status.as_str() {
-        "ok" => {
-            parsed.item.ok_or_else(|| "Item not valid".to_string())?
-        }
-        "error" => {
-            return Err(parsed.message.unwrap_or("Unknown error".into()));
-        }
-        other => {
-            return Err(format!("Unexpected status: {}", other));
-        }
-    };
+    parsed.item.ok_or_else(|| "Item not valid".to_string())
+}
 
-    //toggle todo item
-    todo.done = !todo.done;
+/// Create a new todo item
+/// 
+/// Returns the created todo item including the ID and creation date assigned by the backend.
+/// 
+/// * `todo` - The todo item to add to the database
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn create_todo(todo: TodoItem, api_url: String) -> Result<TodoItem, String> {
+    let client = client();
 
-    //set finish date
-    if todo.done {
-        todo.finish_date = Utc::now()
-    } else {
-        todo.finish_date = Utc.timestamp_opt(0, 0).unwrap();
-    }
+    //url to post a new todo
+    let url = format!("{}/todos", api_url);
 
-    //update todo item
     let response = client
-        .put(&url)
+        .post(&url)
         .json(&todo)
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
 
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    parsed.item.ok_or_else(|| "Item not valid".to_string())
+}
 
-    match parsed.status.as_str() {
-        "ok" => {
-            Ok("Todo status updated".to_string())
-        }
-        "error" => {
-            let msg = parsed.message.unwrap_or("Unknown error".into());
-            Err(msg)
-        }
-        other => {
-            Err(format!("Unexpected status: {}", other))
-        }
+/// Update a todo item
+/// 
+/// Compares the edited todo with the original one and sends only the changed fields as a partial update. The done status is sent with the other fields in the same request, the backend sets the done date. Returns the updated todo item.
+/// 
+/// The update is only applied if the todo still has the version of `original`. If someone else changed the todo in the meantime an error is returned, so their changes are not overwritten.
+/// 
+/// * `todo` - The edited todo item
+/// * `original` - The todo item as it was before editing
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn update_todo(todo: TodoItem, original: TodoItem, api_url: String) -> Result<TodoItem, String> {
+    let client = client();
+
+    let url = format!("{}/todos/{}", api_url, original.id);
+
+    let mut patch = TodoPatch::default();
+
+    if todo.title != original.title {
+        patch.title = Some(todo.title);
+    }
+
+    if todo.content != original.content {
+        patch.content = Some(todo.content);
+    }
+
+    if todo.done != original.done {
+        patch.done = Some(todo.done);
+    }
+
+    if todo.priority != original.priority {
+        patch.priority = Some(todo.priority);
+    }
+
+    if todo.due_date != original.due_date {
+        patch.due_date = Some(todo.due_date);
+    }
+
+    if todo.tags != original.tags {
+        patch.tags = Some(todo.tags);
+    }
+
+    if todo.project_id != original.project_id {
+        patch.project_id = Some(todo.project_id);
+    }
+
+    if todo.parent_id != original.parent_id {
+        patch.parent_id = Some(todo.parent_id);
+    }
+
+    if todo.recurrence != original.recurrence {
+        patch.recurrence = Some(todo.recurrence);
+    }
+
+    let response = client
+        .patch(&url)
+        .header(reqwest::header::IF_MATCH, format!("\"{}\"", original.version))
+        .json(&patch)
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
+
+    //todo was changed since it was loaded
+    if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(CONFLICT_MESSAGE.to_string());
     }
+
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
+
+    parsed.item.ok_or_else(|| "Item not valid".to_string())
 }
 
-/// Create a new todo item
+/// Move a specific todo item to the trash
 /// 
-/// Function sets the creation date to `Utc::now()`.
+/// The todo can be restored with [`restore_todo()`] until it is purged.
 /// 
-/// * `todo` - The todo item to add to the database
+/// * `id` - ID of the todo item to delete
 /// * `api_url` - URL to the backend server
 #[tauri::command]
-pub async fn create_todo(mut todo: TodoItem, api_url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn delete_todo(id: i64, api_url: String) -> Result<String, String> {
+    let client = client();
 
-    //url to post a new todo
-    let url = format!("{}/todos", api_url);
+    //delete todo item based on ID
+    let url = format!("{}/todos/{}", api_url, id);
+
+    let response = client
+        .delete(&url)
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
+
+    parse_response::<TodoItem>(response).await?;
+
+    Ok("Todo moved to trash".to_string())
+}
 
-    //set creation date
-    todo.creation_date = Utc::now();
+/// Restore a todo item from the trash
+/// 
+/// Returns the restored todo item, subtasks deleted together with it are restored too.
+/// 
+/// * `id` - ID of the todo item to restore
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn restore_todo(id: i64, api_url: String) -> Result<TodoItem, String> {
+    let client = client();
+    let url = format!("{}/todos/{}/restore", api_url, id);
 
     let response = client
         .post(&url)
-        .json(&todo)
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
-
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
 
-    match parsed.status.as_str() {
-        "ok" => {
-            Ok("Todo created".to_string())
-        }
-        "error" => {
-            let msg = parsed.message.unwrap_or("Unknown error".into());
-            Err(msg)
-        }
-        other => {
-            Err(format!("Unexpected status: {}", other))
-        }
-    }
+    parsed.item.ok_or_else(|| "Item not valid".to_string())
 }
 
-/// Update a new todo item
+/// Fetch the todos in the trash
 /// 
-/// Get the original todo from the backend. The done date is set to `Utc::now()` if the done status changed to true. The done date is set to `0` if the done status changed to false. 
+/// Works like [`fetch_todos()`], the todos are ordered by deletion, most recent first.
 /// 
-/// * `todo` - The todo item to add to the database
+/// * `params` - Query that is applied at the backend
 /// * `api_url` - URL to the backend server
 #[tauri::command]
-pub async fn update_todo(mut todo: TodoItem, api_url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn fetch_trash(params: QueryParams, api_url: String) -> Result<TodoPage, String> {
+    let client = client();
+    let url = format!("{}/trash", api_url);
+
+    let response = client
+        .get(url)
+        .query(&params)
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
 
-    //get old todo based on id to check if done status changed
-    let url = format!("{}/todos/{}", api_url, todo.id);
+    let parsed: ApiResponse<Vec<TodoItem>> = parse_response(response).await?;
+
+    let items = parsed.items.unwrap_or_default();
+    let total = parsed.total.unwrap_or(items.len() as i64);
+
+    Ok(TodoPage { items, total, next_cursor: parsed.next_cursor })
+}
+
+/// Delete all todo items in the trash for good
+/// 
+/// Returns the number of purged todo items.
+/// 
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn purge_trash(api_url: String) -> Result<u64, String> {
+    let client = client();
+    let url = format!("{}/trash", api_url);
 
     let response = client
-        .get(&url)
+        .delete(&url)
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
 
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    Ok(parsed.deleted.unwrap_or(0))
+}
 
-    let original_todo: TodoItem = match parsed.status.as_str() {
-        "ok" => {
-            parsed.item.ok_or_else(|| "Item not valid".to_string())?
-        }
-        "error" => {
-            return Err(parsed.message.unwrap_or("Unknown error".into()));
-        }
-        other => {
-            return Err(format!("Unexpected status: {}", other));
-        }
-    };
+/// Apply several operations in one request
+/// 
+/// The operations are applied in order in one transaction, if one of them fails none is saved. Returns a result for every operation, see the bulk endpoint of the backend.
+/// 
+/// * `operations` - Create, update, delete or toggle operations
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn bulk_todos(operations: Vec<BulkOperation>, api_url: String) -> Result<Vec<serde_json::Value>, String> {
+    let client = client();
+    let url = format!("{}/todos/bulk", api_url);
 
-    if !original_todo.done && todo.done {
-        todo.finish_date = Utc::now();
-    }
+    let response = client
+        .post(&url)
+        .json(&BulkRequest { operations })
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
 
-    if original_todo.done && !todo.done {
-        todo.finish_date = Utc.timestamp_opt(0, 0).unwrap();
+    //a stale version fails the whole request
+    if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(CONFLICT_MESSAGE.to_string());
     }
 
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
+
+    Ok(parsed.results.unwrap_or_default())
+}
+
+/// Move all todo items that are done to the trash
+/// 
+/// Returns the number of deleted todo items.
+/// 
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn delete_done_todos(api_url: String) -> Result<u64, String> {
+    let client = client();
+    let url = format!("{}/todos", api_url);
+
+    let params = QueryParams { done: Some(DoneFilter::Done), ..Default::default() };
+
     let response = client
-        .put(&url)
-        .json(&todo)
+        .delete(&url)
+        .query(&params)
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+    let parsed: ApiResponse<TodoItem> = parse_response(response).await?;
 
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    Ok(parsed.deleted.unwrap_or(0))
+}
 
-    match parsed.status.as_str() {
-        "ok" => {
-            Ok("Todo updated".to_string())
-        }
-        "error" => {
-            let msg = parsed.message.unwrap_or("Unknown error".into());
-            Err(msg)
-        }
-        other => {
-            Err(format!("Unexpected status: {}", other))
-        }
-    }
+/// Fetch the change history of a todo item
+/// 
+/// Returns the changes, oldest first. Each change holds the fields that changed with their values before and after.
+/// 
+/// * `id` - ID of the todo item
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn fetch_history(id: i64, api_url: String) -> Result<Vec<TodoEvent>, String> {
+    let client = client();
+    let url = format!("{}/todos/{}/history", api_url, id);
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
+
+    let parsed: ApiResponse<Vec<TodoEvent>> = parse_response(response).await?;
+
+    Ok(parsed.items.unwrap_or_default())
 }
 
-/// Delete a specific todo item from database
+/// Undo the last change made by this app
+/// 
+/// Every call goes back one change, eg an edit with [`update_todo()`] or a deletion with [`delete_todo()`].
+/// Returns the todo items that were changed back, a created todo is moved to the trash.
+/// Fails if someone else changed the todos since.
 /// 
-/// * `id` - ID of the todo item to delete
 /// * `api_url` - URL to the backend server
 #[tauri::command]
-pub async fn delete_todo(id: i64, api_url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn undo(api_url: String) -> Result<Vec<TodoItem>, String> {
+    let client = client();
+    let url = format!("{}/undo", api_url);
 
-    //delete todo item based on ID
-    let url = format!("{}/todos/{}", api_url, id);
+    let response = client
+        .post(&url)
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
+
+    let parsed: ApiResponse<Vec<TodoItem>> = parse_response(response).await?;
+
+    Ok(parsed.items.unwrap_or_default())
+}
+
+/// Fetch the todos that changed since the last sync
+/// 
+/// Without `since` all todos are returned. Pass the returned token as `since` to the next sync to get only the changes after this one.
+/// 
+/// * `since` - Token returned by the previous sync
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn sync_todos(since: Option<i64>, api_url: String) -> Result<SyncResult, String> {
+    let client = client();
+    let url = format!("{}/sync", api_url);
 
     let response = client
-        .delete(&url)
+        .get(&url)
+        .query(&SyncParams { since })
         .send()
         .await
         .map_err(|e| { format!("Request error: {}", e) })?;
 
-    let raw_body = response.text().await.map_err(|e| { e.to_string() })?;
+    let parsed: ApiResponse<Vec<TodoItem>> = parse_response(response).await?;
+
+    let token = parsed.token.ok_or_else(|| "Token not valid".to_string())?;
 
-    let parsed: ApiResponse<TodoItem> = serde_json::from_str(&raw_body).map_err(|e| {format!("JSON parse error: {}", e) })?;
+    Ok(SyncResult {
+        items: parsed.items.unwrap_or_default(),
+        tombstones: parsed.tombstones.unwrap_or_default(),
+        token,
+    })
+}
 
-    match parsed.status.as_str() {
-        "ok" => {
-            Ok("Todo deleted".to_string())
+/// Follow the changes made to todos by anyone
+/// 
+/// Opens the event stream of the backend and emits every change to the webview as `todo-event` with a [`TodoNotification`] as payload.
+/// The stream is opened again after it was closed, without missing changes. Calling this again with another URL switches to that backend.
+/// 
+/// * `app` - Handle of the app to emit the events with
+/// * `api_url` - URL to the backend server
+#[tauri::command]
+pub async fn subscribe_events(app: AppHandle, api_url: String) -> Result<(), String> {
+    {
+        let mut current = STREAM_URL.lock().map_err(|e| e.to_string())?;
+        if current.as_deref() == Some(api_url.as_str()) {
+            return Ok(());
         }
-        "error" => {
-            let msg = parsed.message.unwrap_or("Unknown error".into());
-            Err(msg)
+        *current = Some(api_url.clone());
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_id = None;
+
+        //a stream of a previous URL stops once it reconnects
+        while STREAM_URL.lock().map(|current| current.as_deref() == Some(api_url.as_str())).unwrap_or(false) {
+            //the backend might be offline, changes are sent once it is back
+            let _ = read_events(&app, &api_url, &mut last_id).await;
+            tokio::time::sleep(RECONNECT_DELAY).await;
         }
-        other => {
-            Err(format!("Unexpected status: {}", other))
+    });
+
+    Ok(())
+}
+
+/// Read the event stream of the backend until it is closed
+/// 
+/// * `app` - Handle of the app to emit the events with
+/// * `api_url` - URL to the backend server
+/// * `last_id` - ID of the last received event, the stream resumes after it
+async fn read_events(app: &AppHandle, api_url: &str, last_id: &mut Option<i64>) -> Result<(), String> {
+    let client = client();
+    let url = format!("{}/events/stream", api_url);
+
+    let mut request = client.get(&url);
+    if let Some(id) = last_id {
+        request = request.header("Last-Event-ID", id.to_string());
+    }
+
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| { format!("Request error: {}", e) })?;
+
+    if !response.status().is_success() {
+        return Err(format!("Unexpected status: {}", response.status()));
+    }
+
+    //a chunk can end inside a character, only complete events are decoded
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+
+        //events are separated by an empty line, lines starting with a colon only keep the connection open
+        while let Some(end) = buffer.windows(2).position(|bytes| bytes == b"\n\n") {
+            let event: Vec<u8> = buffer.drain(..end + 2).collect();
+            let event = String::from_utf8_lossy(&event);
+
+            //the data of an event can span several lines
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|line| line.strip_prefix(' ').unwrap_or(line))
+                .collect::<Vec<&str>>()
+                .join("\n");
+
+            if let Ok(notification) = serde_json::from_str::<TodoNotification>(&data) {
+                *last_id = Some(notification.event_id);
+                app.emit("todo-event", notification).map_err(|e| e.to_string())?;
+            }
         }
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
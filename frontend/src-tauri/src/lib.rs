@@ -15,6 +15,15 @@ pub fn run() {
             create_todo,
             update_todo,
             delete_todo,
+            restore_todo,
+            fetch_trash,
+            purge_trash,
+            bulk_todos,
+            delete_done_todos,
+            fetch_history,
+            undo,
+            subscribe_events,
+            sync_todos,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
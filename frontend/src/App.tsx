@@ -1,5 +1,6 @@
 import { useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 import { useTheme } from "./components/theme-provider";
 import { H2, Muted, P } from "./components/ui/typography";
@@ -23,6 +24,7 @@ function App() {
   };
 
   const [todos, setTodos] = useState<TodoItem[]>([]);
+  const [total, setTotal] = useState(0);
   const [sorting, setSorting] = useState<SortingState>([]);
   const [menuOpen, setMenuOpen] = useState(false);
   const [statusFilter, setStatusFilter] = useState<boolean | undefined>(undefined);
@@ -46,6 +48,7 @@ function App() {
 
   const PAGE_SIZE = 25;
   const [page, setPage] = useState(0);
+  const pageCount = Math.max(1, Math.ceil(total / PAGE_SIZE));
 
   function mapSortingToQuery(sorting: SortingState): Pick<QueryParams, "sort_by" | "order"> {
     if (!sorting.length) return {}
@@ -62,7 +65,7 @@ function App() {
     try {
       const query = mapSortingToQuery(sorting)
 
-      const result = await invoke<TodoItem[]>("fetch_todos", {
+      const result = await invoke<{ items: TodoItem[], total: number }>("fetch_todos", {
         params: {
           ...query,
           count: PAGE_SIZE,
@@ -73,9 +76,11 @@ function App() {
         apiUrl: appConfig.backendUrl,
       })
 
-      setTodos(result)
+      setTodos(result.items)
+      setTotal(result.total)
     } catch (err: any) {
       setTodos([])
+      setTotal(0)
       toast.error(err.toString())
     }
   };
@@ -84,6 +89,21 @@ function App() {
     if (configLoaded) fetchTodos()
   }, [sorting, page, statusFilter, searchString, appConfig])
 
+  //reload when anyone changes a todo, the listener always calls the latest fetchTodos
+  const fetchTodosRef = useRef(fetchTodos);
+  fetchTodosRef.current = fetchTodos;
+
+  useEffect(() => {
+    if (!configLoaded) return;
+    invoke("subscribe_events", { apiUrl: appConfig.backendUrl })
+      .catch((err) => toast.error("Failed to follow changes: " + err));
+
+    const unlisten = listen("todo-event", () => fetchTodosRef.current());
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [configLoaded, appConfig.backendUrl]);
+
   //reset pagination on sorting
   useEffect(() => {
     if (configLoaded) setPage(0);
@@ -178,21 +198,29 @@ function App() {
                 </PaginationLink>
               </PaginationItem>
 
-              <PaginationItem>
-                <PaginationLink
-                  onClick={(e) => {
-                    e.preventDefault();
-                    setPage(() => 1);
-                  }}>
-                  2
-                </PaginationLink>
-              </PaginationItem>
+              {pageCount > 1 && (
+                <PaginationItem>
+                  <PaginationLink
+                    onClick={(e) => {
+                      e.preventDefault();
+                      setPage(() => 1);
+                    }}>
+                    2
+                  </PaginationLink>
+                </PaginationItem>
+              )}
 
-              <PaginationItem>
-                <PaginationLink>
-                  3
-                </PaginationLink>
-              </PaginationItem>
+              {pageCount > 2 && (
+                <PaginationItem>
+                  <PaginationLink
+                    onClick={(e) => {
+                      e.preventDefault();
+                      setPage(() => 2);
+                    }}>
+                    3
+                  </PaginationLink>
+                </PaginationItem>
+              )}
             </>
           )}
   
@@ -218,15 +246,17 @@ function App() {
                 </PaginationLink>
               </PaginationItem>
 
-              <PaginationItem>
-                <PaginationLink
-                  onClick={(e) => {
-                    e.preventDefault();
-                    setPage((p) => p + 1);
-                  }}>
-                  {page + 2}
-                </PaginationLink>
-              </PaginationItem>
+              {page + 1 < pageCount && (
+                <PaginationItem>
+                  <PaginationLink
+                    onClick={(e) => {
+                      e.preventDefault();
+                      setPage((p) => p + 1);
+                    }}>
+                    {page + 2}
+                  </PaginationLink>
+                </PaginationItem>
+              )}
             </>
           )}
 
@@ -238,8 +268,10 @@ function App() {
             <PaginationNext
               onClick={(e) => {
                 e.preventDefault();
-                setPage((p) => p + 1);
+                setPage((p) => Math.min(pageCount - 1, p + 1));
               }}
+              aria-disabled={page + 1 >= pageCount}
+              className={page + 1 >= pageCount ? "pointer-events-none opacity-50" : ""}
             />
           </PaginationItem>
         </PaginationContent>
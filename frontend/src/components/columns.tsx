@@ -13,8 +13,19 @@ export type TodoItem = {
   done: boolean
   priority: number
   creation_date: number //epoch seconds
-  due_date: number //epoch seconds
-  finish_date: number //epoch seconds
+  due_date: number | null //epoch seconds, null = no due date
+  finish_date: number | null //epoch seconds, null = not finished
+  updated_at?: number | null //epoch seconds, set by the server
+  version?: number //incremented by the server with every change
+  deleted_at?: number | null //epoch seconds, null = not in the trash
+  tags: string[]
+  project_id: number | null //null = inbox
+  parent_id: number | null //null = top-level todo
+  recurrence: string | null //RFC 5545 rule, eg FREQ=WEEKLY;BYDAY=MO
+  children_total?: number
+  children_done?: number
+  children?: TodoItem[] //only set for tree=true
+  snippet?: string //only set for search with snippets=true
 }
 
 export const priorities = [
@@ -83,7 +94,7 @@ export const columns = (
         const id = row.original.id
 
         try {
-          await invoke<string>("toggle_todo_status", { id: id, apiUrl: appConfig.backendUrl })
+          await invoke<TodoItem>("toggle_todo_status", { id: id, done: row.original.done, apiUrl: appConfig.backendUrl })
           await fetchTodos()
         } catch (err: any) {
           toast.error(err.toString())
@@ -132,7 +143,12 @@ export const columns = (
       <DataTableColumnHeader column={column} title="Due" />
     ),
     cell: info => {
-      const date = new Date(info.getValue<number>() * 1000)
+      const dueDate = info.getValue<number | null>()
+      if (dueDate === null) {
+        return <span className="text-muted-foreground">—</span>
+      }
+
+      const date = new Date(dueDate * 1000)
       const now = new Date()
 
       const isOverdue = !info.row.original.done && date < now
@@ -1,6 +1,6 @@
 import { Input } from "./ui/input"
 import { Button } from "./ui/button"
-import { Check, CheckCircle, Circle, CircleFadingPlus, ListRestart, PlusCircle, X } from "lucide-react";
+import { Check, CheckCircle, Circle, CircleFadingPlus, ListRestart, ListX, PlusCircle, X } from "lucide-react";
 import { ButtonGroup } from "./ui/button-group";
 import { Popover, PopoverContent, PopoverTrigger } from "./ui/popover";
 import { Command, CommandGroup, CommandItem, CommandList } from "./ui/command";
@@ -10,6 +10,7 @@ import { Separator } from "./ui/separator";
 export function DataTableToolbar({
   fetchTodos,
   onAdd,
+  onClearDone,
   statusFilter,
   setStatusFilter,
   searchString,
@@ -17,6 +18,7 @@ export function DataTableToolbar({
 }: {
   fetchTodos: () => Promise<void>
   onAdd: () => void
+  onClearDone: () => void
   statusFilter: boolean | undefined
   setStatusFilter: (v: boolean | undefined) => void
   searchString: string | undefined
@@ -107,6 +109,9 @@ export function DataTableToolbar({
         <Button variant="outline" size="icon-sm" aria-label="add" onClick={onAdd}>
           <CircleFadingPlus />
         </Button>
+        <Button variant="outline" size="icon-sm" aria-label="clear done" onClick={onClearDone}>
+          <ListX />
+        </Button>
         <Button variant="outline" size="icon-sm" aria-label="add" onClick={fetchTodos}>
           <ListRestart />
         </Button>
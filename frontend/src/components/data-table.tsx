@@ -19,6 +19,8 @@ import { useState } from "react";
 import { TodoItem } from "./columns";
 import { TodoItemDialog } from "./todo-item-dialog";
 import { AppConfig } from "@/lib/app-config";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
 
 interface DataTableProps {
   columns: ColumnDef<TodoItem, any>[]
@@ -33,16 +35,37 @@ interface DataTableProps {
   appConfig: AppConfig
 }
 
-export type SortBy = "creation_date" | "due_date" | "priority" | "done"
+export type SortBy = "creation_date" | "due_date" | "priority" | "done" | "title" | "finish_date" | "updated_at" | "deleted_at" | "id"
 export type Order = "asc" | "desc"
 
 export interface QueryParams {
   count?: number
   offset?: number
+  cursor?: string
+  sort?: string //comma separated, -field = descending, eg "-priority,due_date,title"
   sort_by?: SortBy
   order?: Order
-  done?: boolean
-  search?: string
+  nulls?: "first" | "last" //position of todos without due date, default last
+  done?: boolean | "any"
+  overdue?: boolean
+  due_before?: number //epoch seconds, exclusive
+  due_after?: number //epoch seconds
+  created_before?: number
+  created_after?: number
+  finished_before?: number
+  finished_after?: number
+  updated_since?: number //epoch seconds, todos changed at or after
+  priority_min?: number
+  priority_max?: number
+  search?: string //full-text search, word* = prefix, "..." = phrase
+  snippets?: boolean
+  tag?: string
+  any_tag?: string //comma separated
+  all_tags?: string //comma separated
+  project?: string //project id or "inbox"
+  top_level?: boolean
+  tree?: boolean
+  trashed?: boolean //todos in the trash instead of the others
 }
 
 export function DataTable({
@@ -75,12 +98,24 @@ export function DataTable({
     setDialogOpen(true)
   }
 
+  //delete all done todos in one request
+  const handleClearDone = async () => {
+    try {
+      const deleted = await invoke<number>("delete_done_todos", { apiUrl: appConfig.backendUrl })
+      toast.success(`Moved ${deleted} done todos to the trash`)
+      await fetchTodos()
+    } catch (err) {
+      toast.error("Failed to delete done todos", { description: String(err) })
+    }
+  }
+
   return (
     <div className="flex flex-col gap-4 h-full">
 
       <DataTableToolbar 
         fetchTodos={fetchTodos} 
         onAdd={handleAddTodo}
+        onClearDone={handleClearDone}
         statusFilter={statusFilter}
         setStatusFilter={setStatusFilter}
         searchString={searchString}
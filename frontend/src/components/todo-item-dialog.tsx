@@ -53,9 +53,14 @@ export function TodoItemDialog({
       setDone(todo.done)
       setPriority(todo.priority)
 
-      const d = new Date(todo.due_date * 1000)
-      setDueDate(d)
-      setDueTime(d.toTimeString().slice(0, 5))
+      if (todo.due_date !== null) {
+        const d = new Date(todo.due_date * 1000)
+        setDueDate(d)
+        setDueTime(d.toTimeString().slice(0, 5))
+      } else {
+        setDueDate(undefined)
+        setDueTime("10:30")
+      }
     } else {
       setTitle("")
       setContent("")
@@ -86,11 +91,6 @@ export function TodoItemDialog({
       return;
     }
 
-    if (!dueDate) {
-      toast.error("Due date is required");
-      return;
-    }
-
     const newTodo: TodoItem = {
       id: todo?.id || 0,
       title,
@@ -98,30 +98,58 @@ export function TodoItemDialog({
       done,
       priority,
       creation_date: 0,
-      finish_date: 0,
-      due_date: mergeDateAndTime(dueDate, dueTime)
+      finish_date: null,
+      due_date: dueDate ? mergeDateAndTime(dueDate, dueTime) : null,
+      tags: todo?.tags ?? [],
+      project_id: todo?.project_id ?? null,
+      parent_id: todo?.parent_id ?? null,
+      recurrence: todo?.recurrence ?? null,
     };
 
     try {
       onOpenChange(false);
       if (todo) {
         // Update existing todo
-        await invoke<string>("update_todo", { todo: newTodo, apiUrl: appConfig.backendUrl })
+        await invoke<TodoItem>("update_todo", { todo: newTodo, original: todo, apiUrl: appConfig.backendUrl })
+        showUndo("Todo saved")
       } else {
         // Create new todo
-        await invoke<any>("create_todo", { todo: newTodo, apiUrl: appConfig.backendUrl })
+        await invoke<TodoItem>("create_todo", { todo: newTodo, apiUrl: appConfig.backendUrl })
       }
       fetchTodos();
     } catch (err) {
-      toast.error("Failed to save todo: " + err);
+      //invalid fields are listed one per line
+      toast.error("Failed to save todo", {
+        description: <span className="whitespace-pre-line">{String(err)}</span>,
+      });
     }
   };
 
+  //the backend undoes the last change made by this app
+  const showUndo = (message: string) => {
+    toast.success(message, {
+      action: {
+        label: "Undo",
+        onClick: async () => {
+          try {
+            await invoke<TodoItem[]>("undo", { apiUrl: appConfig.backendUrl })
+            fetchTodos();
+          } catch (err) {
+            toast.error("Failed to undo: " + err);
+          }
+        },
+      },
+    });
+  }
+
   const handleDelete = async () => {
     try {
       onOpenChange(false);
       await invoke<string>("delete_todo", { id: todo?.id, apiUrl: appConfig.backendUrl })
       fetchTodos();
+
+      //deleted todos stay in the trash until they are purged
+      showUndo("Todo moved to trash")
     } catch (err) {
       toast.error("Failed to delete todo: " + err);
     }